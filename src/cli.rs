@@ -1,21 +1,67 @@
-use clap::Parser;
-use std::path::PathBuf;
+use clap::error::ErrorKind;
+use clap::{ArgMatches, CommandFactory, FromArgMatches, Parser, ValueEnum};
+use crate::file_type::{TypeFilter, TypeGroup};
+use crate::filter::{parse_size, parse_time, FileFilter, FilterRules, Pattern, Rule, RuleKind};
+use std::collections::HashSet;
+use std::io::{IsTerminal, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::SystemTime;
 
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about = None)]
+#[command(
+    author,
+    version,
+    about,
+    long_about = None,
+    override_usage = "cpui [OPTIONS] SOURCE... DESTINATION\n       cpui [OPTIONS] -t DIRECTORY SOURCE...\n       cpui [OPTIONS] --files-from FILE SOURCE_DIR DESTINATION"
+)]
 pub struct Cli {
-    /// Source file or directory
-    #[arg(value_name = "SOURCE")]
-    pub source: PathBuf,
+    /// Source files or directories, followed by the destination unless -t is given
+    #[arg(value_name = "PATH", required = true)]
+    paths: Vec<PathBuf>,
+
+    /// Source files or directories
+    #[arg(skip)]
+    pub sources: Vec<PathBuf>,
 
     /// Destination file or directory
-    #[arg(value_name = "DESTINATION")]
+    #[arg(skip)]
     pub destination: PathBuf,
 
+    /// Copy all SOURCE arguments into DIRECTORY
+    #[arg(short = 't', long, value_name = "DIRECTORY", conflicts_with = "no_target_directory")]
+    pub target_directory: Option<PathBuf>,
+
+    /// Treat DESTINATION as a normal file, never copy into it
+    #[arg(short = 'T', long)]
+    pub no_target_directory: bool,
+
+    /// Use rsync-style path rules: a trailing slash on a source directory
+    /// copies its contents, otherwise the directory itself is copied into DESTINATION
+    #[arg(long, conflicts_with_all = ["target_directory", "no_target_directory"])]
+    pub trailing_slash: bool,
+
+    /// Copy exactly the paths listed in FILE ('-' for stdin), one per line or NUL-separated,
+    /// relative to the single SOURCE directory
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["no_target_directory", "trailing_slash"])]
+    files_from: Option<PathBuf>,
+
+    /// Source directory that the --files-from paths are relative to
+    #[arg(skip)]
+    pub source_root: Option<PathBuf>,
+
     /// Recursively copy directories
     #[arg(short, long)]
     pub recursive: bool,
 
+    /// Descend at most N directory levels below each source directory
+    #[arg(long, value_name = "N")]
+    pub max_depth: Option<usize>,
+
+    /// Stay on the source's file system and skip directories that are other mount points
+    #[arg(short = 'x', long)]
+    pub one_file_system: bool,
+
     /// Preserve file attributes (mode, ownership, timestamps)
     #[arg(long)]
     pub preserve: bool,
@@ -24,13 +70,121 @@ pub struct Cli {
     #[arg(short = 'f', long)]
     pub force: bool,
 
+    /// Move replaced destination files to the trash instead of deleting them
+    #[arg(long)]
+    pub trash: bool,
+
     /// Skip confirmation prompt when using force
     #[arg(short = 'y', long = "yes")]
     pub yes: bool,
 
-    /// Exclude files/directories that match these patterns
+    /// Ask what to do for each file that already exists at the destination
+    #[arg(short = 'i', long)]
+    pub interactive: bool,
+
+    /// Include files/directories matching these glob patterns, relative to each source
     #[arg(long, value_name = "PATTERN", value_delimiter = ',')]
-    pub exclude: Option<Vec<String>>,
+    include: Vec<Pattern>,
+
+    /// Exclude files/directories matching these glob patterns, relative to each source
+    /// (`*`, `**`, `?`, `[abc]`, `/anchored`, `dir-only/`)
+    #[arg(long, value_name = "PATTERN", value_delimiter = ',')]
+    exclude: Vec<Pattern>,
+
+    /// Include files/directories whose path relative to the source matches REGEX
+    #[arg(long, value_name = "REGEX", value_parser = Pattern::regex)]
+    include_regex: Vec<Pattern>,
+
+    /// Exclude files/directories whose path relative to the source matches REGEX
+    #[arg(long, value_name = "REGEX", value_parser = Pattern::regex)]
+    exclude_regex: Vec<Pattern>,
+
+    /// Read include patterns from FILE, one per line ('#' starts a comment)
+    #[arg(long, value_name = "FILE")]
+    include_from: Vec<PathBuf>,
+
+    /// Read exclude patterns from FILE, one per line ('#' starts a comment)
+    #[arg(long, value_name = "FILE")]
+    exclude_from: Vec<PathBuf>,
+
+    /// Add a filter rule: '+ PATTERN' includes, '- PATTERN' excludes.
+    /// All include and exclude rules are checked in command-line order and the first match wins
+    #[arg(long, value_name = "RULE", allow_hyphen_values = true)]
+    filter: Vec<Rule>,
+
+    /// Only copy files of at least SIZE bytes (suffixes: K, M, G, T or KB, MB, GB, TB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    min_size: Option<u64>,
+
+    /// Only copy files of at most SIZE bytes (suffixes: K, M, G, T or KB, MB, GB, TB)
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    max_size: Option<u64>,
+
+    /// Only copy files modified after DATE (YYYY-MM-DD[ HH:MM[:SS]]) or within DURATION (e.g. 7d, 12h)
+    #[arg(long, value_name = "DATE|DURATION", value_parser = parse_time)]
+    newer_than: Option<SystemTime>,
+
+    /// Only copy files modified before DATE (YYYY-MM-DD[ HH:MM[:SS]]) or longer than DURATION ago
+    #[arg(long, value_name = "DATE|DURATION", value_parser = parse_time)]
+    older_than: Option<SystemTime>,
+
+    /// Only copy files modified more recently than FILE
+    #[arg(long, value_name = "FILE")]
+    newer: Option<PathBuf>,
+
+    /// Size and age limits collected from --min-size, --max-size, --newer-than, --older-than and --newer
+    #[arg(skip)]
+    pub file_filter: FileFilter,
+
+    /// Only copy files of these types, judged by extension unless --sniff is given
+    #[arg(long, value_name = "TYPE", value_delimiter = ',')]
+    only_type: Vec<TypeGroup>,
+
+    /// Skip files of these types, judged by extension unless --sniff is given
+    #[arg(long, value_name = "TYPE", value_delimiter = ',')]
+    exclude_type: Vec<TypeGroup>,
+
+    /// Detect file types for --only-type/--exclude-type from the first bytes of each file
+    #[arg(long)]
+    sniff: bool,
+
+    /// Type groups collected from --only-type, --exclude-type and --sniff
+    #[arg(skip)]
+    pub type_filter: TypeFilter,
+
+    /// Skip files listed in .gitignore, .ignore, .git/info/exclude and the global git excludes
+    #[arg(long)]
+    pub gitignore: bool,
+
+    /// Ordered include/exclude rules collected from the glob, regex, file and --filter options
+    #[arg(skip)]
+    pub filters: FilterRules,
+
+    /// Print what would be copied without changing anything
+    #[arg(long, value_name = "FORMAT", num_args = 0..=1, require_equals = true, default_missing_value = "text")]
+    pub dry_run: Option<PlanFormat>,
+
+    /// How to show progress: inline TUI, periodic plain-text lines, newline-delimited
+    /// JSON events, or nothing. Defaults to the TUI when stdout is a terminal and plain text otherwise
+    #[arg(long, value_name = "MODE")]
+    progress: Option<ProgressMode>,
+
+    /// Write --progress=json events to this open file descriptor instead of stdout
+    #[arg(long, value_name = "FD")]
+    pub progress_fd: Option<i32>,
+
+    /// Print each copied file and created directory as 'SOURCE' -> 'DESTINATION',
+    /// above the progress display or on its own with --progress=none
+    #[arg(short = 'v', long, conflicts_with = "quiet")]
+    pub verbose: bool,
+
+    /// Show nothing but errors and questions that need an answer
+    #[arg(short = 'q', long, conflicts_with = "progress")]
+    pub quiet: bool,
+
+    /// Also write the end-of-run summary to PATH as JSON
+    #[arg(long, value_name = "PATH")]
+    pub summary_json: Option<PathBuf>,
 
     /// Hidden test mode with artificial delay (format: test_mode=<type>:<value>)
     /// Example: test_mode=delay:10
@@ -38,6 +192,20 @@ pub struct Cli {
     pub test_mode: Option<String>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum PlanFormat {
+    Text,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ProgressMode {
+    Tui,
+    Plain,
+    Json,
+    None,
+}
+
 #[derive(Debug, Clone)]
 pub enum TestMode {
     Delay(u64),      // Milliseconds delay
@@ -63,12 +231,23 @@ impl Cli {
         }
     }
 
-    pub fn should_exclude(&self, path: &str) -> bool {
-        if let Some(patterns) = &self.exclude {
-            patterns.iter().any(|pattern| path.contains(pattern))
-        } else {
-            false
+    /// 未指定 --progress 时，标准输出是终端才使用 TUI
+    pub fn progress_mode(&self) -> ProgressMode {
+        if self.quiet {
+            return ProgressMode::None;
         }
+        self.progress.unwrap_or_else(|| {
+            if std::io::stdout().is_terminal() {
+                ProgressMode::Tui
+            } else {
+                ProgressMode::Plain
+            }
+        })
+    }
+
+    /// `relative` 是相对于源根目录的路径
+    pub fn should_exclude(&self, relative: &Path, is_dir: bool) -> bool {
+        self.filters.is_excluded(relative, is_dir)
     }
 
     pub fn should_prompt_for_overwrite(&self) -> bool {
@@ -76,6 +255,192 @@ impl Cli {
     }
 }
 
+// 读取模式文件，每行一个模式，忽略空行和以 # 开头的注释
+fn read_pattern_file(path: &Path) -> Result<Vec<Pattern>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot read pattern file '{}': {}", path.display(), e))?;
+
+    content
+        .lines()
+        .map(str::trim_end)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            line.parse()
+                .map_err(|e| format!("invalid pattern in '{}': {}", path.display(), e))
+        })
+        .collect()
+}
+
+// 按照命令行中出现的位置合并 --include、--exclude、--include-regex、--exclude-regex、--include-from、--exclude-from 和 --filter
+fn collect_filter_rules(matches: &ArgMatches) -> Result<FilterRules, String> {
+    let mut rules: Vec<(usize, Rule)> = Vec::new();
+
+    let patterns = [
+        ("include", RuleKind::Include),
+        ("exclude", RuleKind::Exclude),
+        ("include_regex", RuleKind::Include),
+        ("exclude_regex", RuleKind::Exclude),
+    ];
+    for (id, kind) in patterns {
+        if let (Some(patterns), Some(indices)) = (matches.get_many::<Pattern>(id), matches.indices_of(id)) {
+            rules.extend(indices.zip(patterns).map(|(index, pattern)| {
+                (index, Rule { kind, pattern: pattern.clone() })
+            }));
+        }
+    }
+    for (id, kind) in [("include_from", RuleKind::Include), ("exclude_from", RuleKind::Exclude)] {
+        if let (Some(files), Some(indices)) = (matches.get_many::<PathBuf>(id), matches.indices_of(id)) {
+            for (index, file) in indices.zip(files) {
+                for pattern in read_pattern_file(file)? {
+                    rules.push((index, Rule { kind, pattern }));
+                }
+            }
+        }
+    }
+    if let (Some(filters), Some(indices)) = (matches.get_many::<Rule>("filter"), matches.indices_of("filter")) {
+        rules.extend(indices.zip(filters.cloned()));
+    }
+
+    // 稳定排序，同一个文件中的模式保持原有顺序
+    rules.sort_by_key(|(index, _)| *index);
+    Ok(FilterRules::new(rules.into_iter().map(|(_, rule)| rule).collect()))
+}
+
+// 读取 --files-from 列表，含 NUL 时按 NUL 分隔，否则按行分隔
+fn read_files_from(path: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut content = Vec::new();
+    let result = if path == Path::new("-") {
+        std::io::stdin().lock().read_to_end(&mut content)
+    } else {
+        std::fs::File::open(path).and_then(|mut file| file.read_to_end(&mut content))
+    };
+    result.map_err(|e| format!("cannot read file list '{}': {}", path.display(), e))?;
+
+    let separator = if content.contains(&0) { b'\0' } else { b'\n' };
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut seen = HashSet::new();
+    for entry in content.split(|&byte| byte == separator) {
+        let entry = String::from_utf8_lossy(entry);
+        let entry = entry.trim_end_matches('\r').trim_start_matches('/');
+        if entry.is_empty() {
+            continue;
+        }
+
+        let relative: PathBuf = Path::new(entry)
+            .components()
+            .filter(|c| *c != Component::CurDir)
+            .collect();
+        if relative.components().any(|c| c == Component::ParentDir) {
+            return Err(format!("listed path '{}' must not contain '..'", entry));
+        }
+        if relative.as_os_str().is_empty() {
+            continue;
+        }
+        if seen.insert(relative.clone()) {
+            paths.push(relative);
+        }
+    }
+    Ok(paths)
+}
+
+// 把 --files-from 中的路径转换为源根目录下的源，并在复制前报告所有不存在的路径
+fn collect_listed_sources(list: &Path, root: &Path, recursive: bool) -> Result<Vec<PathBuf>, String> {
+    if !root.is_dir() {
+        return Err(format!("--files-from requires SOURCE to be a directory, got '{}'", root.display()));
+    }
+
+    let mut paths = read_files_from(list)?;
+    let missing: Vec<String> = paths
+        .iter()
+        .filter(|relative| root.join(relative).symlink_metadata().is_err())
+        .map(|relative| format!("  {}", relative.display()))
+        .collect();
+    if !missing.is_empty() {
+        return Err(format!(
+            "{} path(s) listed in '{}' do not exist under '{}':\n{}",
+            missing.len(),
+            list.display(),
+            root.display(),
+            missing.join("\n")
+        ));
+    }
+
+    // 递归复制时，已列出目录下的路径会随目录一起复制
+    if recursive {
+        let dirs: HashSet<PathBuf> = paths.iter().filter(|path| root.join(path).is_dir()).cloned().collect();
+        paths.retain(|path| !path.ancestors().skip(1).any(|ancestor| dirs.contains(ancestor)));
+    }
+
+    Ok(paths.into_iter().map(|relative| root.join(relative)).collect())
+}
+
+fn collect_file_filter(cli: &Cli) -> Result<FileFilter, String> {
+    let mut newer_than = cli.newer_than;
+    if let Some(reference) = &cli.newer {
+        let modified = reference
+            .metadata()
+            .and_then(|metadata| metadata.modified())
+            .map_err(|e| format!("cannot read modification time of '{}': {}", reference.display(), e))?;
+        newer_than = newer_than.max(Some(modified));
+    }
+
+    Ok(FileFilter {
+        min_size: cli.min_size,
+        max_size: cli.max_size,
+        newer_than,
+        older_than: cli.older_than,
+    })
+}
+
 pub fn parse_args() -> Cli {
-    Cli::parse()
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    cli.filters = collect_filter_rules(&matches)
+        .unwrap_or_else(|message| Cli::command().error(ErrorKind::Io, message).exit());
+    cli.file_filter = collect_file_filter(&cli)
+        .unwrap_or_else(|message| Cli::command().error(ErrorKind::Io, message).exit());
+    cli.type_filter = TypeFilter {
+        only: cli.only_type.clone(),
+        exclude: cli.exclude_type.clone(),
+        sniff: cli.sniff,
+    };
+
+    // 事件流写到标准输出时不能再混入详细输出
+    if cli.progress == Some(ProgressMode::Json) {
+        if cli.verbose && cli.progress_fd.is_none() {
+            Cli::command()
+                .error(ErrorKind::ArgumentConflict, "--verbose needs --progress-fd when used with --progress=json")
+                .exit();
+        }
+    } else if cli.progress_fd.is_some() {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, "--progress-fd can only be used with --progress=json")
+            .exit();
+    }
+    let mut paths = std::mem::take(&mut cli.paths);
+
+    // 使用 -t 时所有位置参数都是源，否则最后一个是目标
+    if let Some(dir) = &cli.target_directory {
+        cli.destination = dir.clone();
+    } else if paths.len() < 2 {
+        Cli::command()
+            .error(ErrorKind::MissingRequiredArgument, "missing destination after SOURCE")
+            .exit();
+    } else {
+        cli.destination = paths.pop().unwrap();
+    }
+
+    if let Some(list) = &cli.files_from {
+        let [root] = paths.as_slice() else {
+            Cli::command()
+                .error(ErrorKind::WrongNumberOfValues, "--files-from takes exactly one SOURCE directory")
+                .exit();
+        };
+        let sources = collect_listed_sources(list, root, cli.recursive)
+            .unwrap_or_else(|message| Cli::command().error(ErrorKind::Io, message).exit());
+        cli.source_root = Some(root.clone());
+        paths = sources;
+    }
+    cli.sources = paths;
+    cli
 }
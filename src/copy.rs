@@ -1,57 +1,173 @@
 use crate::cli::{Cli, TestMode};
+use crate::trash;
+use crate::walk::{Exclusion, SourceWalk};
 use anyhow::{Result, bail};
+use parking_lot::Mutex;
 use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use tokio::fs::{self, File};
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use walkdir::WalkDir;
+
+// 正在写入的文件（新文件或覆盖时的临时文件）
+static PARTIAL_COPY: Mutex<Option<PathBuf>> = parking_lot::const_mutex(None);
 
 pub struct FileToOverwrite {
     pub path: PathBuf,
     pub is_dir: bool,
 }
 
+fn has_trailing_slash(path: &Path) -> bool {
+    path.as_os_str()
+        .to_string_lossy()
+        .chars()
+        .last()
+        .is_some_and(std::path::is_separator)
+}
+
+/// `--trailing-slash` 时以 `/` 结尾的源目录只复制其内容，多个这样的源可以合并到同一个目标目录
+pub fn copies_contents(src: &Path, cli: &Cli) -> bool {
+    cli.trailing_slash && src.is_dir() && has_trailing_slash(src)
+}
+
+/// 在开始复制前检查目标参数是否与源的数量和选项匹配
+pub fn check_destination(cli: &Cli) -> Result<()> {
+    let dst = &cli.destination;
+
+    if cli.target_directory.is_some() {
+        if !dst.is_dir() {
+            bail!("Target directory '{}' does not exist or is not a directory.", dst.display());
+        }
+    } else if cli.source_root.is_some() {
+        // --files-from 时目标目录不存在会被创建
+        if dst.exists() && !dst.is_dir() {
+            bail!("Target '{}' is not a directory.", dst.display());
+        }
+    } else if cli.no_target_directory {
+        if cli.sources.len() > 1 {
+            bail!("Option -T accepts exactly one source, got {}.", cli.sources.len());
+        }
+    } else if cli.sources.len() > 1 && !dst.is_dir() {
+        // rsync 规则下不存在的目标目录会被创建
+        if cli.trailing_slash && !dst.exists() {
+            return Ok(());
+        }
+        bail!("Target '{}' is not a directory.", dst.display());
+    }
+
+    Ok(())
+}
+
+/// 计算源路径在目标位置对应的路径
+///
+/// 默认情况下目标是已存在的目录时复制到其中，否则按目标路径复制；
+/// `-t` 总是复制到目录中，`-T` 总是按目标路径复制，`--trailing-slash` 使用 rsync 的规则，
+/// `--files-from` 保留列出的路径相对于源根目录的位置。
+pub fn resolve_target(src: &Path, dst: &Path, cli: &Cli) -> Result<PathBuf> {
+    let listed = cli
+        .source_root
+        .as_deref()
+        .and_then(|root| src.strip_prefix(root).ok());
+
+    let into_dir = if cli.target_directory.is_some() {
+        true
+    } else if cli.no_target_directory {
+        false
+    } else if cli.trailing_slash {
+        dst.is_dir() || has_trailing_slash(dst) || src.is_dir()
+    } else {
+        dst.is_dir()
+    };
+
+    let target = if let Some(relative) = listed {
+        dst.join(relative)
+    } else if copies_contents(src, cli) {
+        dst.to_path_buf()
+    } else if into_dir {
+        let name = src.file_name().ok_or_else(|| anyhow::anyhow!("Invalid source name: '{}'", src.display()))?;
+        dst.join(name)
+    } else {
+        dst.to_path_buf()
+    };
+
+    if src.is_dir() && target.exists() && !target.is_dir() {
+        bail!(
+            "Cannot overwrite non-directory '{}' with directory '{}'.",
+            target.display(),
+            src.display()
+        );
+    }
+    if src.is_file() && target.is_dir() {
+        bail!(
+            "Cannot overwrite directory '{}' with non-directory '{}'.",
+            target.display(),
+            src.display()
+        );
+    }
+
+    Ok(target)
+}
+
+/// 源文件按其名字匹配过滤规则并检查大小、修改时间和类型，源目录是遍历的根目录，本身不会被排除
+///
+/// `--files-from` 列出的文件和目录按相对于源根目录的路径匹配过滤规则。
+pub fn is_source_excluded(src: &Path, cli: &Cli) -> bool {
+    let listed = cli
+        .source_root
+        .as_deref()
+        .and_then(|root| src.strip_prefix(root).ok());
+    if let Some(relative) = listed {
+        // 递归复制时只有目录中的内容都不可能被包含才排除整个目录
+        if src.is_dir() && cli.recursive {
+            return cli.filters.excludes_contents(relative);
+        }
+        if src.is_dir() {
+            return cli.should_exclude(relative, true);
+        }
+    }
+    if !src.is_file() {
+        return false;
+    }
+
+    let name = listed.unwrap_or_else(|| src.file_name().map(Path::new).unwrap_or(src));
+    let outside_limits = cli.file_filter.is_active()
+        && src.metadata().is_ok_and(|metadata| !cli.file_filter.matches(&metadata));
+    let wrong_type = cli.type_filter.is_active() && !cli.type_filter.matches(src);
+    cli.should_exclude(name, false) || outside_limits || wrong_type
+}
+
 pub async fn check_overwrites(src: &Path, dst: &Path, recursive: bool, cli: &Cli) -> Result<Vec<FileToOverwrite>> {
     let mut files_to_overwrite = Vec::new();
 
+    if is_source_excluded(src, cli) {
+        return Ok(files_to_overwrite);
+    }
+
     if src.is_file() {
-        let dst_path = if dst.is_dir() {
-            dst.join(src.file_name().ok_or_else(|| anyhow::anyhow!("Invalid source file name"))?)
-        } else {
-            dst.to_path_buf()
-        };
+        let dst_path = resolve_target(src, dst, cli)?;
 
-        if dst_path.exists() && !cli.should_exclude(&dst_path.to_string_lossy()) {
+        if dst_path.exists() {
             files_to_overwrite.push(FileToOverwrite {
                 path: dst_path,
                 is_dir: false,
             });
         }
     } else if recursive && src.is_dir() {
-        let src_name = src.file_name().ok_or_else(|| anyhow::anyhow!("Invalid source directory name"))?;
-        let new_dst = if dst.is_dir() {
-            dst.join(src_name)
-        } else {
-            dst.to_path_buf()
-        };
+        let new_dst = resolve_target(src, dst, cli)?;
 
         // 如果目标目录存在，检查其中会被覆盖的文件
         if new_dst.exists() {
-            for entry in WalkDir::new(src).min_depth(1) {
+            for entry in SourceWalk::new(src, cli) {
                 let entry = entry?;
-                let path = entry.path();
-
-                if cli.should_exclude(&path.to_string_lossy()) {
+                if entry.excluded.is_some() {
                     continue;
                 }
 
-                let relative_path = path.strip_prefix(src)?;
-                let target_path = new_dst.join(relative_path);
+                let target_path = new_dst.join(&entry.relative);
 
                 if target_path.exists() {
                     files_to_overwrite.push(FileToOverwrite {
                         path: target_path,
-                        is_dir: path.is_dir(),
+                        is_dir: entry.is_dir,
                     });
                 }
             }
@@ -61,101 +177,369 @@ pub async fn check_overwrites(src: &Path, dst: &Path, recursive: bool, cli: &Cli
     Ok(files_to_overwrite)
 }
 
-pub async fn get_total_size(path: &Path, recursive: bool, cli: &Cli) -> Result<u64> {
-    let mut total_size = 0;
+/// 复制前扫描源得到的统计
+#[derive(Debug, Default, Clone)]
+pub struct ScanTotals {
+    pub bytes: u64,
+    pub files: u64,
+    pub dirs: u64,
+    pub excluded: u64,
+    // 其中被忽略文件排除的条目数
+    pub ignored: u64,
+    // 其中没有进入遍历的目录数
+    pub pruned: u64,
+    // -x 时跳过的挂载点
+    pub mount_points: Vec<PathBuf>,
+}
+
+impl std::ops::AddAssign for ScanTotals {
+    fn add_assign(&mut self, other: Self) {
+        self.bytes += other.bytes;
+        self.files += other.files;
+        self.dirs += other.dirs;
+        self.excluded += other.excluded;
+        self.ignored += other.ignored;
+        self.pruned += other.pruned;
+        self.mount_points.extend(other.mount_points);
+    }
+}
+
+/// 复制过程中实际完成的操作，用于结束时的总结
+#[derive(Debug, Clone, Default)]
+pub struct CopyStats {
+    pub files_copied: u64,
+    pub dirs_created: u64,
+    pub bytes_copied: u64,
+    pub skipped: u64,
+    // 用户在界面上中止了复制
+    pub aborted: bool,
+}
+
+pub async fn get_total_size(path: &Path, recursive: bool, cli: &Cli) -> Result<ScanTotals> {
+    let mut totals = ScanTotals::default();
+
+    if is_source_excluded(path, cli) {
+        totals.excluded += 1;
+        return Ok(totals);
+    }
 
     if recursive && path.is_dir() {
-        for entry in WalkDir::new(path).min_depth(1) {
+        totals.dirs += 1;
+        for entry in SourceWalk::new(path, cli) {
             let entry = entry?;
-            if entry.path().is_file() {
-                if !cli.should_exclude(&entry.path().to_string_lossy()) {
-                    total_size += entry.metadata()?.len();
+            match entry.excluded {
+                Some(Exclusion::OtherFileSystem) => totals.mount_points.push(entry.path),
+                Some(exclusion) => {
+                    totals.excluded += 1;
+                    if exclusion == Exclusion::IgnoreFile {
+                        totals.ignored += 1;
+                    }
+                    if entry.pruned {
+                        totals.pruned += 1;
+                    }
                 }
+                None if entry.is_file => {
+                    totals.files += 1;
+                    totals.bytes += entry.path.metadata()?.len();
+                }
+                None if entry.is_dir => totals.dirs += 1,
+                None => {}
             }
         }
     } else if path.is_file() {
-        if !cli.should_exclude(&path.to_string_lossy()) {
-            total_size = path.metadata()?.len();
+        totals.files = 1;
+        totals.bytes = path.metadata()?.len();
+    } else if path.is_dir() && cli.source_root.is_some() {
+        totals.dirs = 1;
+    }
+
+    Ok(totals)
+}
+
+/// 交互模式下对单个已存在目标文件的处理方式
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConflictChoice {
+    Overwrite,
+    Skip,
+    Rename(String),
+    KeepNewer,
+}
+
+pub struct ConflictAnswer {
+    pub choice: ConflictChoice,
+    pub apply_to_all: bool,
+}
+
+type NewFileCallback = Box<dyn Fn(&str, u64) + Send + Sync>;
+type ConflictCallback = Box<dyn Fn(&Path, &Path) -> Result<ConflictAnswer> + Send + Sync>;
+type ControlCallback = Box<dyn Fn() -> CopyControl + Send + Sync>;
+type LogCallback = Box<dyn Fn(LogEvent) + Send + Sync>;
+
+/// 记录到活动日志中的事件类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogKind {
+    // 开始复制文件，只出现在 JSON 事件流中
+    Started,
+    Copied,
+    // 创建了目标目录
+    Created,
+    Skipped,
+    Warning,
+    Error,
+}
+
+/// 复制过程中的一个事件，活动日志、详细输出和 JSON 事件流都由它生成
+#[derive(Debug, Clone)]
+pub struct LogEvent {
+    pub kind: LogKind,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    // 文件大小，目录和错误为 0
+    pub size: u64,
+    // 跳过的原因，或者警告和错误的完整说明
+    pub reason: Option<String>,
+}
+
+impl LogEvent {
+    fn new(kind: LogKind, source: &Path, destination: &Path) -> Self {
+        Self {
+            kind,
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            size: 0,
+            reason: None,
+        }
+    }
+
+    fn with_size(mut self, size: u64) -> Self {
+        self.size = size;
+        self
+    }
+
+    fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    /// 不属于某个文件的错误
+    pub fn error(message: String) -> Self {
+        Self {
+            kind: LogKind::Error,
+            source: PathBuf::new(),
+            destination: PathBuf::new(),
+            size: 0,
+            reason: Some(message),
+        }
+    }
+
+    /// 活动日志和详细输出中显示的文字
+    pub fn message(&self) -> String {
+        match self.kind {
+            LogKind::Started | LogKind::Copied | LogKind::Created => {
+                format!("'{}' -> '{}'", self.source.display(), self.destination.display())
+            }
+            LogKind::Skipped => format!(
+                "'{}' ({})",
+                self.destination.display(),
+                self.reason.as_deref().unwrap_or_default()
+            ),
+            LogKind::Warning | LogKind::Error => self.reason.clone().unwrap_or_default(),
         }
     }
+}
+
+/// 进度界面在复制过程中发出的请求，在每个数据块之前检查
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyControl {
+    Continue,
+    Pause,
+    Skip,
+    Abort,
+}
 
-    Ok(total_size)
+// 一个文件的数据复制结束的方式
+enum CopyOutcome {
+    Done,
+    Skipped { remaining: u64 },
+    Aborted,
 }
 
 pub struct ProgressCallback<F> {
     callback: F,
-    on_new_file: Box<dyn Fn(&str, u64) + Send + Sync>,
+    on_new_file: NewFileCallback,
+    on_conflict: Option<ConflictCallback>,
+    on_control: Option<ControlCallback>,
+    on_log: Option<LogCallback>,
+    stats: Mutex<CopyStats>,
+    // "应用到全部" 时记住的选择
+    remembered_choice: Mutex<Option<ConflictChoice>>,
+}
+
+impl<F> ProgressCallback<F>
+where
+    F: Fn(u64) + Send + Sync,
+{
+    pub fn new(callback: F, on_new_file: impl Fn(&str, u64) + Send + Sync + 'static) -> Self {
+        Self {
+            callback,
+            on_new_file: Box::new(on_new_file),
+            on_conflict: None,
+            on_control: None,
+            on_log: None,
+            stats: Mutex::new(CopyStats::default()),
+            remembered_choice: Mutex::new(None),
+        }
+    }
+
+    pub fn with_conflict_handler(
+        mut self,
+        on_conflict: impl Fn(&Path, &Path) -> Result<ConflictAnswer> + Send + Sync + 'static,
+    ) -> Self {
+        self.on_conflict = Some(Box::new(on_conflict));
+        self
+    }
+
+    pub fn with_control(mut self, on_control: impl Fn() -> CopyControl + Send + Sync + 'static) -> Self {
+        self.on_control = Some(Box::new(on_control));
+        self
+    }
+
+    pub fn with_log(mut self, on_log: impl Fn(LogEvent) + Send + Sync + 'static) -> Self {
+        self.on_log = Some(Box::new(on_log));
+        self
+    }
+}
+
+impl<F> ProgressCallback<F> {
+    fn control(&self) -> CopyControl {
+        self.on_control.as_ref().map_or(CopyControl::Continue, |control| control())
+    }
+
+    /// 到目前为止实际完成的操作
+    pub fn stats(&self) -> CopyStats {
+        self.stats.lock().clone()
+    }
+
+    // 记录事件并计入统计
+    fn log(&self, event: LogEvent) {
+        {
+            let mut stats = self.stats.lock();
+            match event.kind {
+                LogKind::Copied => {
+                    stats.files_copied += 1;
+                    stats.bytes_copied += event.size;
+                }
+                LogKind::Created => stats.dirs_created += 1,
+                LogKind::Skipped => stats.skipped += 1,
+                LogKind::Started | LogKind::Warning | LogKind::Error => {}
+            }
+        }
+        if let Some(log) = &self.on_log {
+            log(event);
+        }
+    }
 }
 
-pub async fn copy_path<F>(
+fn is_newer(src: &Path, dst: &Path) -> Result<bool> {
+    Ok(src.metadata()?.modified()? > dst.metadata()?.modified()?)
+}
+
+/// 处理已存在的目标文件，返回实际要写入的路径，`None` 表示跳过该文件
+async fn prepare_destination<F>(
     src: &Path,
     dst: &Path,
-    recursive: bool,
-    preserve: bool,
-    test_mode: TestMode,
     cli: &Cli,
-    progress_callback: F,
-    on_new_file: impl Fn(&str, u64) + Send + Sync + 'static,
-) -> Result<()>
+    callback: &ProgressCallback<F>,
+) -> Result<Option<PathBuf>>
 where
     F: Fn(u64) + Send + Sync,
 {
-    let callback = ProgressCallback {
-        callback: progress_callback,
-        on_new_file: Box::new(on_new_file),
+    if !dst.exists() {
+        return Ok(Some(dst.to_path_buf()));
+    }
+
+    let choice = match &callback.on_conflict {
+        Some(ask) if cli.interactive => {
+            let remembered = callback.remembered_choice.lock().clone();
+            match remembered {
+                Some(choice) => choice,
+                None => {
+                    let answer = ask(src, dst)?;
+                    if answer.apply_to_all && !matches!(answer.choice, ConflictChoice::Rename(_)) {
+                        *callback.remembered_choice.lock() = Some(answer.choice.clone());
+                    }
+                    answer.choice
+                }
+            }
+        }
+        _ if cli.force => ConflictChoice::Overwrite,
+        _ => bail!("Destination '{}' already exists. Use -f to force overwrite.", dst.display()),
     };
 
-    if cli.should_exclude(&src.to_string_lossy()) {
+    match choice {
+        ConflictChoice::Overwrite => {}
+        ConflictChoice::Skip => {
+            callback.log(LogEvent::new(LogKind::Skipped, src, dst).with_reason("destination exists"));
+            return Ok(None);
+        }
+        ConflictChoice::Rename(name) => return Ok(Some(dst.with_file_name(name))),
+        ConflictChoice::KeepNewer => {
+            if !is_newer(src, dst)? {
+                callback.log(LogEvent::new(LogKind::Skipped, src, dst).with_reason("destination is newer"));
+                return Ok(None);
+            }
+        }
+    }
+
+    // 原文件由 copy_file 在复制完成后替换或移入回收站
+    Ok(Some(dst.to_path_buf()))
+}
+
+pub async fn copy_path<F>(src: &Path, dst: &Path, cli: &Cli, callback: &ProgressCallback<F>) -> Result<()>
+where
+    F: Fn(u64) + Send + Sync,
+{
+    let recursive = cli.recursive;
+    let preserve = cli.preserve;
+    let test_mode = cli.get_test_mode();
+
+    if is_source_excluded(src, cli) {
         return Ok(());
     }
 
     if src.is_file() {
-        let dst_path = if dst.is_dir() {
-            dst.join(src.file_name().ok_or_else(|| anyhow::anyhow!("Invalid source file name"))?)
-        } else {
-            dst.to_path_buf()
-        };
+        let dst_path = resolve_target(src, dst, cli)?;
 
-        // 对于文件，仅在目标文件存在时检查
-        if dst_path.exists() && !cli.force {
-            bail!("Destination '{}' already exists. Use -f to force overwrite.", dst_path.display());
+        if let Some(parent) = dst_path.parent() {
+            if cli.trailing_slash || cli.source_root.is_some() {
+                create_dirs(src.parent().unwrap_or(src), parent, callback).await?;
+            }
         }
 
-        if dst_path.exists() && cli.force {
-            fs::remove_file(&dst_path).await?;
+        // 对于文件，仅在目标文件存在时检查
+        match prepare_destination(src, &dst_path, cli, callback).await? {
+            Some(target) => copy_file(src, &target, preserve, cli.trash, test_mode, callback).await?,
+            None => skip_file(src, callback)?,
         }
-
-        copy_file(src, &dst_path, preserve, test_mode, &callback).await?;
     } else if recursive && src.is_dir() {
-        let src_dir_name = src.file_name().ok_or_else(|| anyhow::anyhow!("Invalid source directory name"))?;
-        let new_dst = if dst.is_dir() {
-            dst.join(src_dir_name)
-        } else {
-            dst.to_path_buf()
-        };
+        let new_dst = resolve_target(src, dst, cli)?;
 
         // 创建目标目录（如果不存在）
-        if !new_dst.exists() {
-            fs::create_dir_all(&new_dst).await?;
-        }
+        create_dirs(src, &new_dst, callback).await?;
 
         // 收集需要复制的文件和目录
         let mut files_to_copy = Vec::new();
-        for entry in WalkDir::new(src).min_depth(1) {
+        for entry in SourceWalk::new(src, cli) {
             let entry = entry?;
-            let path = entry.path();
-
-            if cli.should_exclude(&path.to_string_lossy()) {
+            if entry.excluded.is_some() {
                 continue;
             }
 
-            let relative_path = path.strip_prefix(src)?;
-            let target_path = new_dst.join(relative_path);
+            let path = entry.path.as_path();
+            let target_path = new_dst.join(&entry.relative);
 
-            if path.is_dir() {
-                if !target_path.exists() {
-                    fs::create_dir_all(&target_path).await?;
-                }
+            if entry.is_dir {
+                create_dirs(path, &target_path, callback).await?;
                 if preserve {
                     let src_metadata = path.metadata()?;
                     let permissions = src_metadata.permissions();
@@ -182,29 +566,25 @@ where
                         }
                     }
                 }
-            } else if path.is_file() {
-                files_to_copy.push((path.to_path_buf(), target_path));
+            } else if entry.is_file {
+                files_to_copy.push((entry.path, target_path));
             }
         }
 
         // 复制文件
         for (src_path, dst_path) in files_to_copy {
-            if let Some(parent) = dst_path.parent() {
-                if !parent.exists() {
-                    fs::create_dir_all(parent).await?;
-                }
+            // 被排除但仍被遍历的目录在复制其中的文件时才创建
+            if let (Some(src_parent), Some(parent)) = (src_path.parent(), dst_path.parent()) {
+                create_dirs(src_parent, parent, callback).await?;
             }
 
             // 检查每个文件是否需要覆盖
-            if dst_path.exists() && !cli.force {
-                bail!("Destination '{}' already exists. Use -f to force overwrite.", dst_path.display());
-            }
-
-            if dst_path.exists() && cli.force {
-                fs::remove_file(&dst_path).await?;
+            match prepare_destination(&src_path, &dst_path, cli, callback).await? {
+                Some(target) => {
+                    copy_file(&src_path, &target, preserve, cli.trash, test_mode.clone(), callback).await?
+                }
+                None => skip_file(&src_path, callback)?,
             }
-
-            copy_file(&src_path, &dst_path, preserve, test_mode.clone(), &callback).await?;
         }
 
         // 设置目标目录的属性（如果需要）
@@ -234,6 +614,10 @@ where
                 }
             }
         }
+    } else if cli.source_root.is_some() && src.is_dir() {
+        // --files-from 中列出的目录在非递归时只创建目录本身
+        let new_dst = resolve_target(src, dst, cli)?;
+        create_dirs(src, &new_dst, callback).await?;
     } else if src.is_dir() {
         bail!("Source '{}' is a directory. Use -r flag for recursive copy.", src.display());
     } else {
@@ -243,10 +627,49 @@ where
     Ok(())
 }
 
+// 创建目录及其所有不存在的上级目录，每个新建的目录都记录一条日志，
+// 对应的源路径取 src 的同一层级，源路径层级不够时用当前目录代替
+async fn create_dirs<F>(src: &Path, dst: &Path, callback: &ProgressCallback<F>) -> Result<()>
+where
+    F: Fn(u64),
+{
+    let missing: Vec<&Path> = dst
+        .ancestors()
+        .take_while(|dir| !dir.as_os_str().is_empty() && !dir.exists())
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(dst).await?;
+    let sources = src
+        .ancestors()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .chain(std::iter::repeat(Path::new(".")));
+    let created: Vec<(&Path, &Path)> = sources.zip(missing).collect();
+    for (src, dst) in created.into_iter().rev() {
+        callback.log(LogEvent::new(LogKind::Created, src, dst));
+    }
+    Ok(())
+}
+
+// 跳过的文件也计入进度，保证总进度能够到达 100%
+fn skip_file<F>(src: &Path, callback: &ProgressCallback<F>) -> Result<()>
+where
+    F: Fn(u64),
+{
+    let file_size = src.metadata()?.len();
+    let file_name = src.file_name().unwrap_or_default().to_string_lossy();
+    (callback.on_new_file)(&file_name, file_size);
+    (callback.callback)(file_size);
+    Ok(())
+}
+
 async fn copy_file<F>(
     src: &Path,
     dst: &Path,
     preserve: bool,
+    trash: bool,
     test_mode: TestMode,
     callback: &ProgressCallback<F>,
 ) -> Result<()>
@@ -261,19 +684,135 @@ where
         .to_string();
 
     (callback.on_new_file)(&file_name, file_size);
+    callback.log(LogEvent::new(LogKind::Started, src, dst).with_size(file_size));
+
+    // 覆盖已存在的文件时先写入临时文件，复制完成后才替换原文件或把它移入回收站，
+    // 跳过或中止时原文件保持不变
+    let partial = dst.exists().then(|| partial_path(dst));
+    let target = partial.as_deref().unwrap_or(dst);
+
+    *PARTIAL_COPY.lock() = Some(target.to_path_buf());
+    let outcome = copy_contents(src, target, file_size, test_mode, callback).await;
+    PARTIAL_COPY.lock().take();
+    let outcome = match outcome {
+        Ok(outcome) => outcome,
+        Err(e) => {
+            // 出错时同样删除复制了一半的文件
+            let _ = fs::remove_file(target).await;
+            return Err(e);
+        }
+    };
+
+    match outcome {
+        CopyOutcome::Done => {
+            if let Some(partial) = &partial {
+                if let Err(e) = replace_destination(partial, dst, trash, callback).await {
+                    let _ = fs::remove_file(partial).await;
+                    return Err(e);
+                }
+            }
+            callback.log(LogEvent::new(LogKind::Copied, src, dst).with_size(file_size));
+        }
+        CopyOutcome::Skipped { remaining } => {
+            // 跳过时删除复制了一半的文件
+            fs::remove_file(target).await?;
+            (callback.callback)(remaining);
+            let reason = "skipped by user, partial copy removed";
+            callback.log(LogEvent::new(LogKind::Skipped, src, dst).with_size(file_size).with_reason(reason));
+            return Ok(());
+        }
+        CopyOutcome::Aborted => {
+            callback.stats.lock().aborted = true;
+            fs::remove_file(target).await?;
+            bail!("Copy aborted by user.");
+        }
+    }
+
+    if preserve {
+        let src_metadata = src.metadata()?;
+        let permissions = src_metadata.permissions();
+        tokio::fs::set_permissions(dst, permissions).await?;
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let atime = filetime::FileTime::from_unix_time(src_metadata.atime(), 0);
+            let mtime = filetime::FileTime::from_unix_time(src_metadata.mtime(), 0);
+            filetime::set_file_times(dst, atime, mtime)?;
+        }
+
+        #[cfg(windows)]
+        {
+            use std::os::windows::fs::MetadataExt;
+            if let (Ok(atime), Ok(mtime)) = (
+                src_metadata.last_access_time().try_into(),
+                src_metadata.last_write_time().try_into(),
+            ) {
+                let atime = filetime::FileTime::from_windows_file_time(atime);
+                let mtime = filetime::FileTime::from_windows_file_time(mtime);
+                filetime::set_file_times(dst, atime, mtime)?;
+            }
+        }
+    }
+
+    Ok(())
+}
 
+// 用复制完成的临时文件替换原文件，需要时先把原文件移入回收站
+async fn replace_destination<F>(partial: &Path, dst: &Path, trash: bool, callback: &ProgressCallback<F>) -> Result<()>
+where
+    F: Fn(u64),
+{
+    if trash {
+        let trashed = trash::move_to_trash(dst)?;
+        let reason = format!("moved '{}' to trash as '{}'", dst.display(), trashed.display());
+        callback.log(LogEvent::new(LogKind::Warning, dst, &trashed).with_reason(reason));
+    }
+    fs::rename(partial, dst).await?;
+    Ok(())
+}
+
+/// 删除正在写入的文件，Ctrl+C 直接结束进程前调用，避免留下复制了一半的文件
+pub fn remove_partial_copy() {
+    if let Some(path) = PARTIAL_COPY.lock().take() {
+        let _ = std::fs::remove_file(path);
+    }
+}
+
+// 与目标同目录的隐藏临时文件，保证重命名不跨文件系统
+fn partial_path(dst: &Path) -> PathBuf {
+    let name = dst.file_name().unwrap_or_default().to_string_lossy();
+    dst.with_file_name(format!(".{}.cpui-partial", name))
+}
+
+// 逐块复制文件内容，每块之前检查暂停、跳过和中止请求
+async fn copy_contents<F>(
+    src: &Path,
+    dst: &Path,
+    file_size: u64,
+    test_mode: TestMode,
+    callback: &ProgressCallback<F>,
+) -> Result<CopyOutcome>
+where
+    F: Fn(u64),
+{
     let mut src_file = File::open(src).await?;
     let mut dst_file = File::create(dst).await?;
 
     let mut buffer = vec![0; 1024 * 1024]; // 1MB buffer
+    let mut copied = 0;
 
     match test_mode {
         TestMode::Delay(ms) => loop {
+            if let Some(outcome) = check_control(callback, file_size.saturating_sub(copied)).await {
+                return Ok(outcome);
+            }
             let n = src_file.read(&mut buffer).await?;
             if n == 0 {
                 break;
             }
             dst_file.write_all(&buffer[..n]).await?;
+            copied += n as u64;
             (callback.callback)(n as u64);
             tokio::time::sleep(Duration::from_millis(ms)).await;
         },
@@ -282,6 +821,9 @@ where
             let mut start_time = Instant::now();
 
             loop {
+                if let Some(outcome) = check_control(callback, file_size.saturating_sub(copied)).await {
+                    return Ok(outcome);
+                }
                 let n = src_file.read(&mut buffer[..chunk_size as usize]).await?;
                 if n == 0 {
                     break;
@@ -296,45 +838,119 @@ where
                     start_time = Instant::now();
                 }
 
+                copied += n as u64;
                 (callback.callback)(n as u64);
             }
         }
         TestMode::None => loop {
+            if let Some(outcome) = check_control(callback, file_size.saturating_sub(copied)).await {
+                return Ok(outcome);
+            }
             let n = src_file.read(&mut buffer).await?;
             if n == 0 {
                 break;
             }
             dst_file.write_all(&buffer[..n]).await?;
+            copied += n as u64;
             (callback.callback)(n as u64);
         },
     }
 
-    if preserve {
-        let src_metadata = src.metadata()?;
-        let permissions = src_metadata.permissions();
-        tokio::fs::set_permissions(dst, permissions).await?;
+    Ok(CopyOutcome::Done)
+}
 
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::MetadataExt;
-            let atime = filetime::FileTime::from_unix_time(src_metadata.atime(), 0);
-            let mtime = filetime::FileTime::from_unix_time(src_metadata.mtime(), 0);
-            filetime::set_file_times(dst, atime, mtime)?;
+// 暂停时在这里等待，返回 None 表示继续复制
+async fn check_control<F>(callback: &ProgressCallback<F>, remaining: u64) -> Option<CopyOutcome> {
+    loop {
+        match callback.control() {
+            CopyControl::Continue => return None,
+            CopyControl::Pause => tokio::time::sleep(Duration::from_millis(100)).await,
+            CopyControl::Skip => return Some(CopyOutcome::Skipped { remaining }),
+            CopyControl::Abort => return Some(CopyOutcome::Aborted),
         }
+    }
+}
 
-        #[cfg(windows)]
-        {
-            use std::os::windows::fs::MetadataExt;
-            if let (Ok(atime), Ok(mtime)) = (
-                src_metadata.last_access_time().try_into(),
-                src_metadata.last_write_time().try_into(),
-            ) {
-                let atime = filetime::FileTime::from_windows_file_time(atime);
-                let mtime = filetime::FileTime::from_windows_file_time(mtime);
-                filetime::set_file_times(dst, atime, mtime)?;
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    // 临时目录中的 d/（含一个文件）、文件 f 和已存在的空目录 out
+    struct Fixture {
+        root: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let root = std::env::temp_dir().join(format!("cpui-test-{}-{}", std::process::id(), name));
+            let _ = std::fs::remove_dir_all(&root);
+            std::fs::create_dir_all(root.join("d")).unwrap();
+            std::fs::create_dir_all(root.join("out")).unwrap();
+            std::fs::write(root.join("d").join("x"), "x").unwrap();
+            std::fs::write(root.join("f"), "f").unwrap();
+            Self { root }
+        }
+
+        fn path(&self, relative: &str) -> PathBuf {
+            PathBuf::from(format!("{}/{}", self.root.display(), relative))
+        }
+
+        fn resolve(&self, flags: &[&str], src: &str, dst: &str) -> Result<PathBuf> {
+            let args = ["cpui"].iter().chain(flags).chain(&["SRC", "DST"]);
+            let cli = Cli::try_parse_from(args).unwrap();
+            resolve_target(&self.path(src), &self.path(dst), &cli)
         }
     }
 
-    Ok(())
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.root);
+        }
+    }
+
+    #[test]
+    fn default_copies_into_existing_directories() {
+        let fixture = Fixture::new("default");
+        assert_eq!(fixture.resolve(&[], "f", "out").unwrap(), fixture.path("out/f"));
+        assert_eq!(fixture.resolve(&[], "f", "new").unwrap(), fixture.path("new"));
+        assert_eq!(fixture.resolve(&[], "d", "out").unwrap(), fixture.path("out/d"));
+        assert_eq!(fixture.resolve(&[], "d", "new").unwrap(), fixture.path("new"));
+    }
+
+    #[test]
+    fn target_directory_flags() {
+        let fixture = Fixture::new("target-directory");
+        assert_eq!(fixture.resolve(&["-t", "out"], "f", "out").unwrap(), fixture.path("out/f"));
+        assert_eq!(fixture.resolve(&["-t", "out"], "d", "out").unwrap(), fixture.path("out/d"));
+        assert_eq!(fixture.resolve(&["-T"], "d", "out").unwrap(), fixture.path("out"));
+        assert_eq!(fixture.resolve(&["-T"], "f", "new").unwrap(), fixture.path("new"));
+    }
+
+    #[test]
+    fn trailing_slash_follows_rsync() {
+        let fixture = Fixture::new("trailing-slash");
+        let flags = &["--trailing-slash"];
+        assert_eq!(fixture.resolve(flags, "d/", "out").unwrap(), fixture.path("out"));
+        assert_eq!(fixture.resolve(flags, "d/", "new").unwrap(), fixture.path("new"));
+        assert_eq!(fixture.resolve(flags, "d", "new").unwrap(), fixture.path("new/d"));
+        assert_eq!(fixture.resolve(flags, "f", "new/").unwrap(), fixture.path("new/f"));
+        assert_eq!(fixture.resolve(flags, "f", "new").unwrap(), fixture.path("new"));
+
+        let cli = Cli::try_parse_from(["cpui", "--trailing-slash", "SRC", "DST"]).unwrap();
+        assert!(copies_contents(&fixture.path("d/"), &cli));
+        assert!(!copies_contents(&fixture.path("d"), &cli));
+        assert!(!copies_contents(&fixture.path("f/"), &cli));
+    }
+
+    #[test]
+    fn directory_and_file_cannot_replace_each_other() {
+        let fixture = Fixture::new("type-mismatch");
+        let error = fixture.resolve(&["-T"], "d", "f").unwrap_err();
+        assert!(error.to_string().starts_with("Cannot overwrite non-directory"));
+        let error = fixture.resolve(&["-T"], "f", "out").unwrap_err();
+        assert!(error.to_string().starts_with("Cannot overwrite directory"));
+        let error = fixture.resolve(&[], "d", "f").unwrap_err();
+        assert!(error.to_string().starts_with("Cannot overwrite non-directory"));
+    }
 }
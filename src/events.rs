@@ -0,0 +1,134 @@
+use crate::copy::{LogEvent, LogKind, ScanTotals};
+use crate::summary::Summary;
+use anyhow::Result;
+use parking_lot::Mutex;
+use serde::Serialize;
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// `--progress=json` 输出的事件，每个事件占一行
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum Event<'a> {
+    ScanStarted {
+        sources: Vec<Cow<'a, str>>,
+        destination: Cow<'a, str>,
+    },
+    ScanFinished {
+        files: u64,
+        dirs: u64,
+        bytes: u64,
+        excluded: u64,
+    },
+    FileStarted {
+        source: Cow<'a, str>,
+        destination: Cow<'a, str>,
+        size: u64,
+    },
+    Progress {
+        bytes: u64,
+        total_bytes: u64,
+        files_done: u64,
+        total_files: u64,
+        bytes_per_sec: f64,
+    },
+    FileFinished {
+        source: Cow<'a, str>,
+        destination: Cow<'a, str>,
+        size: u64,
+    },
+    DirCreated {
+        source: Cow<'a, str>,
+        destination: Cow<'a, str>,
+    },
+    Skip {
+        source: Cow<'a, str>,
+        destination: Cow<'a, str>,
+        reason: &'a str,
+    },
+    Warning {
+        source: Cow<'a, str>,
+        destination: Cow<'a, str>,
+        message: &'a str,
+    },
+    Error {
+        message: &'a str,
+    },
+    Done(&'a Summary),
+}
+
+impl<'a> Event<'a> {
+    pub fn scan_started(sources: &'a [PathBuf], destination: &'a Path) -> Self {
+        Event::ScanStarted {
+            sources: sources.iter().map(|source| source.to_string_lossy()).collect(),
+            destination: destination.to_string_lossy(),
+        }
+    }
+
+    pub fn scan_finished(totals: &ScanTotals) -> Self {
+        Event::ScanFinished {
+            files: totals.files,
+            dirs: totals.dirs,
+            bytes: totals.bytes,
+            excluded: totals.excluded,
+        }
+    }
+
+    pub fn from_log(event: &'a LogEvent) -> Self {
+        let source = event.source.to_string_lossy();
+        let destination = event.destination.to_string_lossy();
+        let reason = event.reason.as_deref().unwrap_or_default();
+        match event.kind {
+            LogKind::Started => Event::FileStarted { source, destination, size: event.size },
+            LogKind::Copied => Event::FileFinished { source, destination, size: event.size },
+            LogKind::Created => Event::DirCreated { source, destination },
+            LogKind::Skipped => Event::Skip { source, destination, reason },
+            LogKind::Warning => Event::Warning { source, destination, message: reason },
+            LogKind::Error => Event::Error { message: reason },
+        }
+    }
+}
+
+/// 事件流的输出端，复制前的扫描、渲染线程和最后的总结共用
+#[derive(Clone)]
+pub struct EventStream {
+    out: Arc<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl EventStream {
+    /// 写到标准输出，或者 `--progress-fd` 指定的已打开的描述符
+    pub fn new(fd: Option<i32>) -> Result<Self> {
+        let out: Box<dyn Write + Send> = match fd {
+            Some(fd) => Box::new(open_fd(fd)?),
+            None => Box::new(io::stdout()),
+        };
+        Ok(Self {
+            out: Arc::new(Mutex::new(out)),
+        })
+    }
+
+    pub fn emit(&self, event: &Event) -> io::Result<()> {
+        let mut out = self.out.lock();
+        serde_json::to_writer(&mut *out, event)?;
+        writeln!(out)?;
+        out.flush()
+    }
+}
+
+#[cfg(unix)]
+fn open_fd(fd: i32) -> Result<std::fs::File> {
+    use std::os::unix::io::FromRawFd;
+
+    // 先确认描述符已经打开，避免接管一个无效的编号
+    if unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+        anyhow::bail!("Invalid --progress-fd {}: {}", fd, io::Error::last_os_error());
+    }
+    Ok(unsafe { std::fs::File::from_raw_fd(fd) })
+}
+
+#[cfg(not(unix))]
+fn open_fd(fd: i32) -> Result<std::fs::File> {
+    anyhow::bail!("Cannot write events to file descriptor {}: --progress-fd is only supported on Unix.", fd)
+}
@@ -0,0 +1,201 @@
+use clap::ValueEnum;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// 内置的文件类型分组，默认按扩展名判断
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TypeGroup {
+    Images,
+    Video,
+    Audio,
+    Archives,
+    Source,
+}
+
+impl TypeGroup {
+    fn extensions(self) -> &'static [&'static str] {
+        match self {
+            TypeGroup::Images => &[
+                "jpg", "jpeg", "png", "gif", "bmp", "tif", "tiff", "webp", "heic", "heif", "avif", "svg", "ico",
+                "psd", "raw", "cr2", "cr3", "nef", "arw", "dng", "orf", "rw2",
+            ],
+            TypeGroup::Video => &[
+                "mp4", "m4v", "mov", "avi", "mkv", "webm", "wmv", "flv", "mpg", "mpeg", "3gp", "m2ts", "vob", "ogv",
+            ],
+            TypeGroup::Audio => &[
+                "mp3", "m4a", "aac", "flac", "wav", "ogg", "oga", "opus", "wma", "aif", "aiff", "alac", "mid", "midi",
+            ],
+            TypeGroup::Archives => &[
+                "zip", "tar", "gz", "tgz", "bz2", "tbz", "tbz2", "xz", "txz", "zst", "tzst", "7z", "rar", "lz", "lzma",
+                "cab", "iso", "jar", "deb", "rpm",
+            ],
+            TypeGroup::Source => &[
+                "rs", "c", "h", "cc", "cpp", "cxx", "hh", "hpp", "hxx", "cs", "go", "java", "kt", "kts", "scala",
+                "swift", "m", "mm", "py", "pyi", "rb", "php", "pl", "pm", "lua", "js", "mjs", "cjs", "jsx", "ts",
+                "tsx", "mts", "cts", "vue", "svelte", "dart", "zig", "nim", "hs", "ml", "mli", "ex", "exs", "erl",
+                "clj", "jl", "r", "sql", "sh", "bash", "zsh", "fish", "ps1", "bat", "html", "htm", "css", "scss",
+                "sass", "less",
+            ],
+        }
+    }
+
+    /// 按扩展名（不区分大小写）判断类型
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        Self::value_variants()
+            .iter()
+            .copied()
+            .find(|group| group.extensions().contains(&extension.as_str()))
+    }
+
+    /// 根据文件开头的特征字节判断类型，无法识别时返回 `None`
+    pub fn from_magic(header: &[u8]) -> Option<Self> {
+        let starts = |magic: &[u8]| header.starts_with(magic);
+        let at = |offset: usize, magic: &[u8]| header.get(offset..offset + magic.len()) == Some(magic);
+
+        // RIFF 和 ISO BMFF（ftyp）容器需要看子类型
+        if starts(b"RIFF") {
+            return match header.get(8..12)? {
+                b"WEBP" => Some(TypeGroup::Images),
+                b"AVI " => Some(TypeGroup::Video),
+                b"WAVE" => Some(TypeGroup::Audio),
+                _ => None,
+            };
+        }
+        if at(4, b"ftyp") {
+            return match header.get(8..12)? {
+                b"heic" | b"heix" | b"mif1" | b"msf1" | b"avif" => Some(TypeGroup::Images),
+                b"M4A " | b"M4B " => Some(TypeGroup::Audio),
+                _ => Some(TypeGroup::Video),
+            };
+        }
+
+        if starts(b"\x89PNG\r\n\x1a\n")
+            || starts(b"\xff\xd8\xff")
+            || starts(b"GIF87a")
+            || starts(b"GIF89a")
+            || is_bmp(header)
+            || starts(b"II*\0")
+            || starts(b"MM\0*")
+            || starts(b"\0\0\x01\0")
+            || starts(b"8BPS")
+        {
+            Some(TypeGroup::Images)
+        } else if starts(b"\x1a\x45\xdf\xa3")
+            || starts(b"FLV\x01")
+            || starts(b"\0\0\x01\xba")
+            || starts(b"\0\0\x01\xb3")
+        {
+            Some(TypeGroup::Video)
+        } else if starts(b"ID3")
+            || starts(b"\xff\xfb")
+            || starts(b"\xff\xf3")
+            || starts(b"\xff\xf2")
+            || starts(b"fLaC")
+            || starts(b"OggS")
+            || (starts(b"FORM") && (at(8, b"AIFF") || at(8, b"AIFC")))
+            || starts(b"MThd")
+        {
+            Some(TypeGroup::Audio)
+        } else if starts(b"PK\x03\x04")
+            || starts(b"PK\x05\x06")
+            || starts(b"\x1f\x8b")
+            || starts(b"BZh")
+            || starts(b"\xfd7zXZ\0")
+            || starts(b"7z\xbc\xaf\x27\x1c")
+            || starts(b"Rar!\x1a\x07")
+            || starts(b"\x28\xb5\x2f\xfd")
+            || starts(b"MSCF")
+            || at(257, b"ustar")
+        {
+            Some(TypeGroup::Archives)
+        } else if starts(b"#!") {
+            Some(TypeGroup::Source)
+        } else {
+            None
+        }
+    }
+
+    /// 读取文件开头判断真实类型；源代码没有特征字节，仍然按扩展名识别
+    pub fn sniff(path: &Path) -> Option<Self> {
+        let mut header = Vec::with_capacity(262);
+        if let Ok(file) = File::open(path) {
+            let _ = file.take(262).read_to_end(&mut header);
+        }
+
+        Self::from_magic(&header).or_else(|| Self::from_extension(path).filter(|group| *group == TypeGroup::Source))
+    }
+}
+
+// "BM" 太短，文本文件也可能以它开头，所以还要检查文件头中的各个字段是否合理
+fn is_bmp(header: &[u8]) -> bool {
+    let u32_at = |offset: usize| {
+        header
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+    let (Some(file_size), Some(reserved), Some(data_offset), Some(dib_size)) =
+        (u32_at(2), u32_at(6), u32_at(10), u32_at(14))
+    else {
+        return false;
+    };
+
+    // 14 字节的文件头之后是已知长度之一的 DIB 头，像素数据在两个头之后
+    header.starts_with(b"BM")
+        && reserved == 0
+        && matches!(dib_size, 12 | 16 | 40 | 52 | 56 | 64 | 108 | 124)
+        && data_offset >= 14 + dib_size
+        && file_size >= data_offset
+}
+
+/// `--only-type` / `--exclude-type` 的组合
+#[derive(Debug, Clone, Default)]
+pub struct TypeFilter {
+    pub only: Vec<TypeGroup>,
+    pub exclude: Vec<TypeGroup>,
+    pub sniff: bool,
+}
+
+impl TypeFilter {
+    pub fn is_active(&self) -> bool {
+        !self.only.is_empty() || !self.exclude.is_empty()
+    }
+
+    pub fn matches(&self, path: &Path) -> bool {
+        let group = if self.sniff {
+            TypeGroup::sniff(path)
+        } else {
+            TypeGroup::from_extension(path)
+        };
+
+        let selected = self.only.is_empty() || group.is_some_and(|group| self.only.contains(&group));
+        let excluded = group.is_some_and(|group| self.exclude.contains(&group));
+        selected && !excluded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bmp_header(file_size: u32, dib_size: u32) -> Vec<u8> {
+        let mut header = b"BM".to_vec();
+        header.extend_from_slice(&file_size.to_le_bytes());
+        header.extend_from_slice(&[0; 4]);
+        header.extend_from_slice(&(14 + dib_size).to_le_bytes());
+        header.extend_from_slice(&dib_size.to_le_bytes());
+        header.resize(64, 0);
+        header
+    }
+
+    #[test]
+    fn bmp_needs_a_plausible_header() {
+        assert_eq!(TypeGroup::from_magic(&bmp_header(1_000, 40)), Some(TypeGroup::Images));
+        assert_eq!(TypeGroup::from_magic(&bmp_header(1_000, 124)), Some(TypeGroup::Images));
+        assert_eq!(TypeGroup::from_magic(&bmp_header(1_000, 41)), None);
+        assert_eq!(TypeGroup::from_magic(&bmp_header(20, 40)), None);
+        assert_eq!(TypeGroup::from_magic(b"BMW service notes\nchange the oil every year\n"), None);
+        assert_eq!(TypeGroup::from_magic(b"BM"), None);
+    }
+}
@@ -0,0 +1,431 @@
+use crate::format::DateTime;
+use globset::{GlobBuilder, GlobMatcher};
+use regex::Regex;
+use std::fs::Metadata;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime};
+
+/// 与 .gitignore 相同规则的路径模式，匹配相对于源根目录的路径
+///
+/// - 不含 `/` 的模式匹配任意层级的名字，例如 `*.log`
+/// - 以 `/` 开头或中间含 `/` 的模式锚定在源根目录，例如 `/build`、`docs/*.md`
+/// - 以 `/` 结尾的模式只匹配目录，例如 `target/`
+/// - 支持 `*`、`**`、`?` 和 `[abc]` 字符类
+///
+/// 也可以用 [`Pattern::regex`] 创建正则表达式模式，在相对路径中搜索匹配。
+#[derive(Debug, Clone)]
+pub struct Pattern {
+    matcher: Matcher,
+    dir_only: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Matcher {
+    Glob(GlobMatcher),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// 正则表达式模式，匹配以 `/` 分隔的相对路径，需要完整匹配时使用 `^` 和 `$`
+    pub fn regex(pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            matcher: Matcher::Regex(Regex::new(pattern)?),
+            dir_only: false,
+        })
+    }
+
+    pub fn matches(&self, relative: &Path, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        match &self.matcher {
+            Matcher::Glob(glob) => glob.is_match(relative),
+            Matcher::Regex(regex) => {
+                let path = relative.to_string_lossy();
+                if std::path::MAIN_SEPARATOR == '/' {
+                    regex.is_match(&path)
+                } else {
+                    regex.is_match(&path.replace(std::path::MAIN_SEPARATOR, "/"))
+                }
+            }
+        }
+    }
+}
+
+impl FromStr for Pattern {
+    type Err = globset::Error;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let mut glob = pattern.trim();
+        let dir_only = glob.len() > 1 && glob.ends_with('/');
+        if dir_only {
+            glob = &glob[..glob.len() - 1];
+        }
+
+        let anchored = glob.contains('/');
+        let glob = glob.trim_start_matches('/');
+        let glob = if anchored || glob.starts_with("**/") {
+            glob.to_string()
+        } else {
+            format!("**/{}", glob)
+        };
+
+        let matcher = GlobBuilder::new(&glob)
+            .literal_separator(true)
+            .backslash_escape(true)
+            .build()?
+            .compile_matcher();
+
+        Ok(Self {
+            matcher: Matcher::Glob(matcher),
+            dir_only,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuleKind {
+    Include,
+    Exclude,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub kind: RuleKind,
+    pub pattern: Pattern,
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// rsync 风格的规则：`+ PATTERN` 包含，`- PATTERN` 排除
+    fn from_str(rule: &str) -> Result<Self, Self::Err> {
+        let (kind, pattern) = if let Some(pattern) = rule.strip_prefix("+ ") {
+            (RuleKind::Include, pattern)
+        } else if let Some(pattern) = rule.strip_prefix("- ") {
+            (RuleKind::Exclude, pattern)
+        } else {
+            return Err(format!("filter rule must start with '+ ' or '- ': '{}'", rule));
+        };
+
+        let pattern = pattern.parse().map_err(|e: globset::Error| e.to_string())?;
+        Ok(Self { kind, pattern })
+    }
+}
+
+/// 过滤规则对一个路径的判断结果，`NoMatch` 时交给忽略文件决定
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    Include,
+    Exclude,
+    NoMatch,
+}
+
+/// 按顺序检查的包含/排除规则，第一条匹配的规则生效，没有规则匹配时包含
+#[derive(Debug, Clone, Default)]
+pub struct FilterRules {
+    rules: Vec<Rule>,
+}
+
+impl FilterRules {
+    pub fn new(rules: Vec<Rule>) -> Self {
+        Self { rules }
+    }
+
+    fn first_match(&self, path: &Path, is_dir: bool, limit: usize) -> Option<(usize, RuleKind)> {
+        self.rules[..limit]
+            .iter()
+            .position(|rule| rule.pattern.matches(path, is_dir))
+            .map(|index| (index, self.rules[index].kind))
+    }
+
+    /// 从源根目录开始逐级检查 `relative` 的每一层目录
+    ///
+    /// 被排除的目录下的内容同样被排除，除非排在该排除规则之前的包含规则选中了它们，
+    /// 这样 `--include '*.rs' --exclude '*'` 仍然能找到深层目录中的 `.rs` 文件。
+    pub fn decide(&self, relative: &Path, is_dir: bool) -> Decision {
+        self.walk_ancestors(relative, is_dir, false)
+    }
+
+    /// 目录 `dir` 下的所有内容是否一定被排除，此时遍历无需进入该目录
+    pub fn excludes_contents(&self, dir: &Path) -> bool {
+        self.walk_ancestors(dir, true, true) == Decision::Exclude
+    }
+
+    pub fn has_includes(&self) -> bool {
+        self.rules.iter().any(|rule| rule.kind == RuleKind::Include)
+    }
+
+    // contents 为 true 时把 relative 当作某个后代的上级目录来检查
+    fn walk_ancestors(&self, relative: &Path, is_dir: bool, contents: bool) -> Decision {
+        if self.rules.is_empty() {
+            return Decision::NoMatch;
+        }
+
+        let mut components: Vec<&Path> = relative
+            .ancestors()
+            .take_while(|path| !path.as_os_str().is_empty())
+            .collect();
+        components.reverse();
+
+        let mut limit = self.rules.len();
+        let last = components.len().saturating_sub(1);
+        for (depth, path) in components.into_iter().enumerate() {
+            let is_last = !contents && depth == last;
+            match self.first_match(path, !is_last || is_dir, limit) {
+                Some((_, RuleKind::Include)) if is_last => return Decision::Include,
+                Some((_, RuleKind::Exclude)) if is_last => return Decision::Exclude,
+                Some((_, RuleKind::Include)) => {}
+                Some((index, RuleKind::Exclude)) => {
+                    let rescued = self.rules[..index].iter().any(|rule| rule.kind == RuleKind::Include);
+                    if !rescued {
+                        return Decision::Exclude;
+                    }
+                    limit = index;
+                }
+                None if is_last && limit < self.rules.len() => return Decision::Exclude,
+                None => {}
+            }
+        }
+        Decision::NoMatch
+    }
+
+    pub fn is_excluded(&self, relative: &Path, is_dir: bool) -> bool {
+        self.decide(relative, is_dir) == Decision::Exclude
+    }
+}
+
+/// 解析大小，例如 `512`、`10K`、`1.5M`、`2GiB`、`100MB`
+///
+/// 不带单位时为字节，`K`/`M`/`G`/`T`（及 `KiB` 等）按 1024 进位，`KB`/`MB` 等按 1000 进位。
+pub fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}'", value))?;
+    let multiplier: u64 = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 1,
+        "K" | "KIB" => 1 << 10,
+        "M" | "MIB" => 1 << 20,
+        "G" | "GIB" => 1 << 30,
+        "T" | "TIB" => 1 << 40,
+        "KB" => 1_000,
+        "MB" => 1_000_000,
+        "GB" => 1_000_000_000,
+        "TB" => 1_000_000_000_000,
+        _ => return Err(format!("invalid size unit in '{}'", value)),
+    };
+
+    Ok((number * multiplier as f64) as u64)
+}
+
+/// 解析时间点：本地日期 `YYYY-MM-DD[ HH:MM[:SS]]`，或者距现在的时长，例如 `7d`、`12h`、`1w2d`
+pub fn parse_time(value: &str) -> Result<SystemTime, String> {
+    let value = value.trim();
+    parse_duration(value)
+        .and_then(|duration| SystemTime::now().checked_sub(duration))
+        .or_else(|| parse_date(value))
+        .ok_or_else(|| format!("invalid date or duration '{}', expected YYYY-MM-DD[ HH:MM[:SS]] or e.g. 7d", value))
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = 0u64;
+    let mut number = String::new();
+
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3_600,
+            'd' => 86_400,
+            'w' => 604_800,
+            _ => return None,
+        };
+        // 数值过大时视为无效，而不是溢出
+        total = total.checked_add(number.parse::<u64>().ok()?.checked_mul(unit)?)?;
+        number.clear();
+    }
+
+    // 必须以单位结尾，且至少有一段
+    (number.is_empty() && !value.is_empty()).then(|| Duration::from_secs(total))
+}
+
+fn parse_date(value: &str) -> Option<SystemTime> {
+    let (date, time) = match value.split_once([' ', 'T']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let mut date = date.splitn(3, '-').map(|part| part.parse::<u32>().ok());
+    let (year, month, day) = (date.next()??, date.next()??, date.next()??);
+
+    let mut time = time.unwrap_or("0:0").split(':').map(|part| part.parse::<u32>().ok());
+    let (hour, minute, second) = (time.next()??, time.next()??, time.next().unwrap_or(Some(0))?);
+    if time.next().is_some() {
+        return None;
+    }
+
+    DateTime {
+        year: year as i64,
+        month,
+        day,
+        hour,
+        minute,
+        second,
+    }
+    .to_system_time()
+}
+
+/// 按大小和修改时间过滤普通文件
+#[derive(Debug, Clone, Default)]
+pub struct FileFilter {
+    pub min_size: Option<u64>,
+    pub max_size: Option<u64>,
+    pub newer_than: Option<SystemTime>,
+    pub older_than: Option<SystemTime>,
+}
+
+impl FileFilter {
+    pub fn is_active(&self) -> bool {
+        self.min_size.is_some() || self.max_size.is_some() || self.newer_than.is_some() || self.older_than.is_some()
+    }
+
+    pub fn matches(&self, metadata: &Metadata) -> bool {
+        let size = metadata.len();
+        if self.min_size.is_some_and(|min| size < min) || self.max_size.is_some_and(|max| size > max) {
+            return false;
+        }
+
+        if self.newer_than.is_some() || self.older_than.is_some() {
+            let Ok(modified) = metadata.modified() else {
+                return false;
+            };
+            if self.newer_than.is_some_and(|time| modified <= time)
+                || self.older_than.is_some_and(|time| modified >= time)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[&str]) -> FilterRules {
+        FilterRules::new(rules.iter().map(|rule| rule.parse().unwrap()).collect())
+    }
+
+    fn matches(pattern: &str, path: &str, is_dir: bool) -> bool {
+        pattern.parse::<Pattern>().unwrap().matches(Path::new(path), is_dir)
+    }
+
+    #[test]
+    fn unanchored_pattern_matches_at_any_depth() {
+        assert!(matches("build", "build", true));
+        assert!(matches("build", "a/build", true));
+        assert!(matches("*.log", "a/b/c.log", false));
+        assert!(!matches("*.log", "a.log/c", false));
+    }
+
+    #[test]
+    fn anchored_pattern_matches_from_root_only() {
+        assert!(matches("/build", "build", true));
+        assert!(!matches("/build", "a/build", true));
+        assert!(matches("docs/*.md", "docs/x.md", false));
+        assert!(!matches("docs/*.md", "a/docs/x.md", false));
+        assert!(!matches("docs/*.md", "docs/sub/x.md", false));
+        assert!(matches("docs/**/*.md", "docs/sub/x.md", false));
+    }
+
+    #[test]
+    fn dir_only_pattern_skips_files() {
+        assert!(matches("target/", "target", true));
+        assert!(!matches("target/", "target", false));
+        assert!(matches("target/", "a/target", true));
+
+        let filter = rules(&["- target/"]);
+        assert_eq!(filter.decide(Path::new("target/x"), false), Decision::Exclude);
+        assert_eq!(filter.decide(Path::new("target"), false), Decision::NoMatch);
+    }
+
+    #[test]
+    fn include_rescues_files_under_excluded_dirs() {
+        let filter = rules(&["+ *.rs", "- *"]);
+        assert_eq!(filter.decide(Path::new("a/b/c.rs"), false), Decision::Include);
+        assert_eq!(filter.decide(Path::new("a/b/c.txt"), false), Decision::Exclude);
+        assert_eq!(filter.decide(Path::new("main.rs"), false), Decision::Include);
+        // 目录本身被排除，只在复制其中的文件时创建
+        assert_eq!(filter.decide(Path::new("a"), true), Decision::Exclude);
+    }
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let filter = rules(&["- build/", "+ *.rs"]);
+        assert_eq!(filter.decide(Path::new("build/x.rs"), false), Decision::Exclude);
+        assert_eq!(filter.decide(Path::new("src/x.rs"), false), Decision::Include);
+        assert_eq!(filter.decide(Path::new("src/x.txt"), false), Decision::NoMatch);
+    }
+
+    #[test]
+    fn rules_after_rescued_exclude_are_ignored() {
+        // a 被 "- a/" 排除后只有排在它之前的规则还能选中后代
+        let filter = rules(&["+ *.rs", "- a/", "+ *.txt"]);
+        assert_eq!(filter.decide(Path::new("a/x.rs"), false), Decision::Include);
+        assert_eq!(filter.decide(Path::new("a/x.txt"), false), Decision::Exclude);
+        assert_eq!(filter.decide(Path::new("b/x.txt"), false), Decision::Include);
+    }
+
+    #[test]
+    fn pruning_only_when_nothing_can_be_rescued() {
+        let filter = rules(&["- target/"]);
+        assert!(filter.excludes_contents(Path::new("target")));
+        assert!(filter.excludes_contents(Path::new("target/debug")));
+        assert!(!filter.excludes_contents(Path::new("src")));
+
+        let filter = rules(&["+ *.rs", "- *"]);
+        assert!(!filter.excludes_contents(Path::new("a")));
+        assert!(!filter.excludes_contents(Path::new("a/b")));
+
+        let filter = rules(&["- build/", "+ *.rs"]);
+        assert!(filter.excludes_contents(Path::new("build")));
+        assert!(!filter.excludes_contents(Path::new("src")));
+    }
+
+    #[test]
+    fn oversized_durations_are_rejected() {
+        assert_eq!(parse_duration("1w2d"), Some(Duration::from_secs(9 * 86_400)));
+        assert_eq!(parse_duration("99999999999999999999s"), None);
+        assert_eq!(parse_duration("30000000000000000w"), None);
+        assert!(parse_time("30000000000000000w").is_err());
+    }
+
+    #[test]
+    fn impossible_dates_are_rejected() {
+        assert!(parse_time("2024-02-29").is_ok());
+        assert!(parse_time("2024-02-30").is_err());
+        assert!(parse_time("2023-02-29").is_err());
+        assert!(parse_time("2024-04-31").is_err());
+        assert!(parse_time("2024-13-01").is_err());
+        assert!(parse_time("2024-01-01 24:00").is_err());
+    }
+
+    #[test]
+    fn no_rules_never_decide() {
+        let filter = FilterRules::default();
+        assert_eq!(filter.decide(Path::new("a/b"), false), Decision::NoMatch);
+        assert!(!filter.excludes_contents(Path::new("a")));
+    }
+}
@@ -0,0 +1,181 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.2} {}", value, UNITS[unit])
+    }
+}
+
+/// 带千位分隔符的计数，例如 `1,234`
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// `MM:SS`，超过一小时时为 `H:MM:SS`
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+    } else {
+        format!("{:02}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// 本地时间的日历表示，Unix 上使用系统时区，其他平台退回到 UTC
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+}
+
+impl DateTime {
+    pub fn from_system_time(time: SystemTime) -> Self {
+        let secs = match time.duration_since(UNIX_EPOCH) {
+            Ok(d) => d.as_secs() as i64,
+            Err(e) => -(e.duration().as_secs() as i64),
+        };
+
+        #[cfg(unix)]
+        {
+            let t = secs as libc::time_t;
+            let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+            if !unsafe { libc::localtime_r(&t, &mut tm) }.is_null() {
+                return Self {
+                    year: tm.tm_year as i64 + 1900,
+                    month: tm.tm_mon as u32 + 1,
+                    day: tm.tm_mday as u32,
+                    hour: tm.tm_hour as u32,
+                    minute: tm.tm_min as u32,
+                    second: tm.tm_sec as u32,
+                };
+            }
+        }
+
+        Self::from_unix_utc(secs)
+    }
+
+    fn from_unix_utc(secs: i64) -> Self {
+        let days = secs.div_euclid(86_400);
+        let rem = secs.rem_euclid(86_400);
+
+        // 公历日期换算（Howard Hinnant 的 civil_from_days）
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z.rem_euclid(146_097);
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year,
+            month,
+            day,
+            hour: (rem / 3600) as u32,
+            minute: (rem % 3600 / 60) as u32,
+            second: (rem % 60) as u32,
+        }
+    }
+
+    /// 把本地时间转换回 `SystemTime`，日期无效时返回 `None`
+    pub fn to_system_time(self) -> Option<SystemTime> {
+        let leap_year = self.year % 4 == 0 && (self.year % 100 != 0 || self.year % 400 == 0);
+        let days_in_month = match self.month {
+            2 if leap_year => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        let valid_date = (1..=12).contains(&self.month) && (1..=days_in_month).contains(&self.day);
+        let valid_time = self.hour < 24 && self.minute < 60 && self.second < 61;
+        if !valid_date || !valid_time {
+            return None;
+        }
+
+        #[cfg(unix)]
+        let secs = {
+            let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+            let year = libc::c_int::try_from(self.year - 1900).ok()?;
+            tm.tm_year = year;
+            tm.tm_mon = self.month as libc::c_int - 1;
+            tm.tm_mday = self.day as libc::c_int;
+            tm.tm_hour = self.hour as libc::c_int;
+            tm.tm_min = self.minute as libc::c_int;
+            tm.tm_sec = self.second as libc::c_int;
+            tm.tm_isdst = -1;
+            // 失败时返回 -1，但 -1 也可能是合法时间，所以用 tm_wday 是否被填写来区分
+            tm.tm_wday = -1;
+            let secs = unsafe { libc::mktime(&mut tm) };
+            if secs == -1 && tm.tm_wday == -1 {
+                return None;
+            }
+            // mktime 会把越界的日期顺延，例如 2 月 30 日变成 3 月 1 日
+            let normalized = (tm.tm_year, tm.tm_mon, tm.tm_mday);
+            if normalized != (year, self.month as libc::c_int - 1, self.day as libc::c_int) {
+                return None;
+            }
+            secs as i64
+        };
+
+        #[cfg(not(unix))]
+        let secs = {
+            // 公历日期换算（Howard Hinnant 的 days_from_civil）
+            let year = self.year - i64::from(self.month <= 2);
+            let era = year.div_euclid(400);
+            let yoe = year.rem_euclid(400);
+            let mp = (self.month as i64 + 9) % 12;
+            let doy = (153 * mp + 2) / 5 + self.day as i64 - 1;
+            let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+            let days = era * 146_097 + doe - 719_468;
+            days * 86_400 + self.hour as i64 * 3600 + self.minute as i64 * 60 + self.second as i64
+        };
+
+        if secs >= 0 {
+            UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+        } else {
+            UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+        }
+    }
+
+    /// `YYYY-MM-DDThh:mm:ss`，不带时区
+    pub fn iso8601(&self) -> String {
+        format!(
+            "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
+
+impl fmt::Display for DateTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+            self.year, self.month, self.day, self.hour, self.minute, self.second
+        )
+    }
+}
@@ -0,0 +1,131 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+const GIT_IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+const CPUI_IGNORE_FILE: &str = ".cpuiignore";
+
+/// 遍历一个源目录时使用的忽略文件，按 git 的作用域规则逐级加载
+///
+/// 每个目录中的 `.cpuiignore`（以及启用 `--gitignore` 时的 `.gitignore` 和 `.ignore`）
+/// 只作用于该目录及其子目录，越深的文件优先级越高，同一目录中 `.cpuiignore` 优先；
+/// 启用 `--gitignore` 时之后依次是源目录之上（同一仓库内）的忽略文件、
+/// `.git/info/exclude` 和全局的 git excludes。
+pub struct IgnoreFiles {
+    root: PathBuf,
+    names: Vec<&'static str>,
+    base: Vec<Gitignore>,
+    dirs: HashMap<PathBuf, Option<Gitignore>>,
+}
+
+impl IgnoreFiles {
+    pub fn new(root: &Path, gitignore: bool) -> Self {
+        let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+        let mut names = Vec::new();
+        let mut base = Vec::new();
+
+        if gitignore {
+            names.extend_from_slice(GIT_IGNORE_FILES);
+        }
+        names.push(CPUI_IGNORE_FILE);
+
+        let repo = gitignore
+            .then(|| root.ancestors().find(|dir| dir.join(".git").exists()))
+            .flatten();
+        if let Some(repo) = repo {
+            for dir in root.ancestors().skip(1) {
+                if !dir.starts_with(repo) {
+                    break;
+                }
+                base.extend(load(dir, GIT_IGNORE_FILES));
+            }
+
+            let exclude = repo.join(".git").join("info").join("exclude");
+            if exclude.is_file() {
+                let mut builder = GitignoreBuilder::new(repo);
+                builder.add(&exclude);
+                base.extend(builder.build().ok());
+            }
+        }
+
+        if gitignore {
+            let (global, _) = Gitignore::global();
+            if !global.is_empty() {
+                base.push(global);
+            }
+        }
+
+        Self {
+            root,
+            names,
+            base,
+            dirs: HashMap::new(),
+        }
+    }
+
+    /// `relative` 是相对于源根目录的路径，被忽略目录下的内容同样被忽略
+    pub fn is_ignored(&mut self, relative: &Path, is_dir: bool) -> bool {
+        let mut components: Vec<&Path> = relative
+            .ancestors()
+            .take_while(|path| !path.as_os_str().is_empty())
+            .collect();
+        components.reverse();
+
+        let last = components.len().saturating_sub(1);
+        components
+            .into_iter()
+            .enumerate()
+            .any(|(depth, path)| self.matched(path, depth < last || is_dir))
+    }
+
+    fn matched(&mut self, relative: &Path, is_dir: bool) -> bool {
+        let path = self.root.join(relative);
+
+        // 由近到远检查每一级目录中的忽略文件，第一个给出结果的文件生效
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        for dir in parent.ancestors() {
+            if let Some(gitignore) = self.dir_matcher(dir) {
+                match gitignore.matched(&path, is_dir) {
+                    Match::Ignore(_) => return true,
+                    Match::Whitelist(_) => return false,
+                    Match::None => {}
+                }
+            }
+        }
+
+        for gitignore in &self.base {
+            match gitignore.matched(&path, is_dir) {
+                Match::Ignore(_) => return true,
+                Match::Whitelist(_) => return false,
+                Match::None => {}
+            }
+        }
+        false
+    }
+
+    fn dir_matcher(&mut self, dir: &Path) -> Option<&Gitignore> {
+        if !self.dirs.contains_key(dir) {
+            let matcher = load(&self.root.join(dir), &self.names);
+            self.dirs.insert(dir.to_path_buf(), matcher);
+        }
+        self.dirs.get(dir).and_then(Option::as_ref)
+    }
+}
+
+fn load(dir: &Path, names: &[&str]) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in names {
+        let file = dir.join(name);
+        if file.is_file() {
+            builder.add(file);
+            found = true;
+        }
+    }
+
+    if !found {
+        return None;
+    }
+    builder.build().ok().filter(|gitignore| !gitignore.is_empty())
+}
@@ -1,27 +1,38 @@
 mod cli;
 mod copy;
+mod events;
+mod file_type;
+mod filter;
+mod format;
+mod ignore_files;
+mod plan;
 mod progress;
+mod summary;
+mod trash;
+mod walk;
 
-use anyhow::Result;
-use parking_lot::Mutex;
+use anyhow::{bail, Result};
 use progress::CopyProgress;
+use std::collections::HashMap;
 use std::io::{self, Write};
-use std::sync::Arc;
+use std::path::PathBuf;
+use summary::Summary;
 use tokio::signal::ctrl_c;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 
-async fn confirm_overwrite(files: &[copy::FileToOverwrite]) -> Result<bool> {
-    println!("\nThe following items will be overwritten:");
+async fn confirm_overwrite(files: &[copy::FileToOverwrite], out: &mut dyn Write) -> Result<bool> {
+    writeln!(out, "\nThe following items will be overwritten:")?;
     for file in files {
-        println!(
+        writeln!(
+            out,
             "  {} {}",
             if file.is_dir { "DIR:" } else { "FILE:" },
             file.path.display()
-        );
+        )?;
     }
 
-    print!("\nDo you want to proceed? [y/N] ");
-    io::stdout().flush()?;
+    write!(out, "\nDo you want to proceed? [y/N] ")?;
+    out.flush()?;
 
     let mut input = String::new();
     io::stdin().read_line(&mut input)?;
@@ -32,69 +43,146 @@ async fn confirm_overwrite(files: &[copy::FileToOverwrite]) -> Result<bool> {
 #[tokio::main]
 async fn main() -> Result<()> {
     let args = cli::parse_args();
-    let test_mode = args.get_test_mode();
-
-    // 如果指定了force，检查将被覆盖的文件
-    if args.force {
-        let files_to_overwrite =
-            copy::check_overwrites(&args.source, &args.destination, args.recursive, &args).await?;
-
-        // 如果有文件要被覆盖，且需要确认
-        if !files_to_overwrite.is_empty() && args.should_prompt_for_overwrite() {
-            if !confirm_overwrite(&files_to_overwrite).await? {
-                println!("Operation cancelled.");
-                return Ok(());
+
+    copy::check_destination(&args)?;
+    let destination = args.destination.as_path();
+
+    // 不同的源不能落到同一个目标路径上，只复制内容的源目录除外，它们像 rsync 一样合并
+    let mut targets: HashMap<PathBuf, &PathBuf> = HashMap::new();
+    for source in &args.sources {
+        let target = copy::resolve_target(source, destination, &args)?;
+        if let Some(previous) = targets.insert(target.clone(), source) {
+            if copy::copies_contents(previous, &args) && copy::copies_contents(source, &args) {
+                continue;
+            }
+            bail!(
+                "Cannot copy both '{}' and '{}' to '{}'.",
+                previous.display(),
+                source.display(),
+                target.display()
+            );
+        }
+    }
+
+    if let Some(format) = args.dry_run {
+        let plan = plan::build_plan(&args).await?;
+        plan.print(format)?;
+        return Ok(());
+    }
+
+    // 只有 TUI 模式下标准输出留给提示，其他模式的标准输出是事件流或 -v 的复制列表
+    let progress_mode = args.progress_mode();
+    let mut prompt_out: Box<dyn Write> = match progress_mode {
+        cli::ProgressMode::Tui => Box::new(io::stdout()),
+        _ => Box::new(io::stderr()),
+    };
+
+    // 在开始复制前检查所有源会覆盖的文件，交互模式下改为逐个文件询问
+    if !args.interactive {
+        let mut files_to_overwrite = Vec::new();
+        for source in &args.sources {
+            files_to_overwrite
+                .extend(copy::check_overwrites(source, destination, args.recursive, &args).await?);
+        }
+
+        if !args.force {
+            if let Some(file) = files_to_overwrite.iter().find(|file| !file.is_dir) {
+                bail!("Destination '{}' already exists. Use -f to force overwrite.", file.path.display());
             }
+        } else if !files_to_overwrite.is_empty()
+            && args.should_prompt_for_overwrite()
+            && !confirm_overwrite(&files_to_overwrite, &mut prompt_out).await?
+        {
+            writeln!(prompt_out, "Operation cancelled.")?;
+            return Ok(());
         }
     }
 
-    // Calculate total size
-    let total_size = copy::get_total_size(&args.source, args.recursive, &args).await?;
-    let progress = Arc::new(Mutex::new(CopyProgress::new(total_size)?));
+    // JSON 模式下从扫描开始就输出事件
+    let event_stream = match progress_mode {
+        cli::ProgressMode::Json => Some(events::EventStream::new(args.progress_fd)?),
+        _ => None,
+    };
+    if let Some(event_stream) = &event_stream {
+        event_stream.emit(&events::Event::scan_started(&args.sources, destination))?;
+    }
+
+    // Calculate total size across all sources
+    let mut totals = copy::ScanTotals::default();
+    for source in &args.sources {
+        totals += copy::get_total_size(source, args.recursive, &args).await?;
+    }
+    if let Some(event_stream) = &event_stream {
+        event_stream.emit(&events::Event::scan_finished(&totals))?;
+    }
+    let total_size = totals.bytes;
+    let mut progress = CopyProgress::new(&totals, progress_mode, args.verbose, event_stream.clone())?;
 
     // Set initial file/directory name
-    let display_name = args
-        .source
-        .file_name()
-        .unwrap_or_default()
-        .to_string_lossy();
-    progress.lock().set_current_file(&display_name, total_size);
-
-    // Create clones for callbacks
-    let progress_for_inc = Arc::clone(&progress);
-    let progress_for_file = Arc::clone(&progress);
-
-    // 修改信号处理逻辑
-    let progress_for_signal = Arc::clone(&progress);
+    let display_name = match args.sources.as_slice() {
+        [source] => source.file_name().unwrap_or_default().to_string_lossy().to_string(),
+        sources => format!("{} items", sources.len()),
+    };
+    progress.handle().set_title(&display_name, total_size);
+
+    // 复制过程只通过句柄发布计数，绘制和终端输入都由渲染任务负责
+    let progress_for_inc = progress.handle();
+    let progress_for_file = progress.handle();
+    let progress_for_conflict = progress.handle();
+    let progress_for_control = progress.handle();
+    let progress_for_log = progress.handle();
+
+    // 渲染任务会恢复终端并退出，正在等待输入时由这里兜底
+    let progress_for_signal = progress.handle();
     tokio::spawn(async move {
         if let Ok(()) = ctrl_c().await {
-            let _ = progress_for_signal.lock().finish();
+            progress_for_signal.interrupt();
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            copy::remove_partial_copy();
             std::process::exit(0);
         }
     });
 
-    // Start the copy operation with exclude patterns
-    let result = copy::copy_path(
-        &args.source,
-        &args.destination,
-        args.recursive,
-        args.preserve,
-        test_mode,
-        &args,
-        move |n| progress_for_inc.lock().inc_current(n),
-        move |name, size| progress_for_file.lock().set_current_file(name, size),
+    let callback = copy::ProgressCallback::new(
+        move |n| progress_for_inc.inc_current(n),
+        move |name, size| progress_for_file.set_current_file(name, size),
     )
-    .await;
+    .with_conflict_handler(move |src, dst| Ok(progress_for_conflict.ask_conflict(src, dst)?))
+    .with_control(move || progress_for_control.control())
+    .with_log(move |event| progress_for_log.log(event));
 
-    // 确保在完成或出错时正确清理
-    let mut progress = progress.lock();
-    if let Err(e) = result {
-        progress.finish()?;
-        return Err(e);
+    // Start the copy operation with exclude patterns
+    let start_time = Instant::now();
+    let mut result = Ok(());
+    for source in &args.sources {
+        result = copy::copy_path(source, destination, &args, &callback).await;
+        if result.is_err() {
+            break;
+        }
+    }
+
+    // 结束后查看日志的时间不计入耗时
+    let elapsed = start_time.elapsed();
+
+    // 确保在完成或出错时正确清理，错误也记录到日志中
+    if let Err(e) = &result {
+        progress.handle().log(copy::LogEvent::error(e.to_string()));
     }
     progress.finish()?;
 
-    // 给用户一些时间看到完成状态
-    tokio::time::sleep(Duration::from_secs(1)).await;
-    Ok(())
+    let summary = Summary::new(callback.stats(), &totals, elapsed, result.as_ref().err());
+    let json_result = match &args.summary_json {
+        Some(path) => summary.write_json(path),
+        None => Ok(()),
+    };
+    // 总结属于进度显示：JSON 模式作为最后一个事件，TUI 写到标准输出，其他模式和纯文本进度一样写到标准错误
+    if let Some(event_stream) = &event_stream {
+        event_stream.emit(&events::Event::Done(&summary))?;
+    } else if progress.mode() == cli::ProgressMode::Tui {
+        summary.write_text(&mut io::stdout().lock())?;
+    } else if !args.quiet {
+        summary.write_text(&mut io::stderr().lock())?;
+    }
+    result?;
+    json_result
 }
@@ -0,0 +1,244 @@
+use crate::cli::{Cli, PlanFormat};
+use crate::copy::{is_source_excluded, resolve_target};
+use crate::format::format_bytes;
+use crate::walk::SourceWalk;
+use anyhow::{bail, Result};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    CreateDir,
+    Copy,
+    Overwrite,
+    Skip,
+    Exclude,
+    SetAttributes,
+}
+
+impl Action {
+    fn label(self) -> &'static str {
+        match self {
+            Action::CreateDir => "mkdir",
+            Action::Copy => "copy",
+            Action::Overwrite => "overwrite",
+            Action::Skip => "skip",
+            Action::Exclude => "exclude",
+            Action::SetAttributes => "attrs",
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlanEntry {
+    pub action: Action,
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PlanTotals {
+    pub dirs_to_create: usize,
+    pub files_to_copy: usize,
+    pub files_to_overwrite: usize,
+    pub skipped: usize,
+    pub excluded: usize,
+    pub attribute_changes: usize,
+    pub bytes: u64,
+}
+
+/// 试运行时生成的完整复制计划，不会修改文件系统
+#[derive(Debug, Default, Serialize)]
+pub struct Plan {
+    pub entries: Vec<PlanEntry>,
+    pub totals: PlanTotals,
+    #[serde(skip)]
+    planned_dirs: HashSet<PathBuf>,
+}
+
+impl Plan {
+    fn push(&mut self, action: Action, source: &Path, destination: &Path, size: Option<u64>, reason: Option<String>) {
+        match action {
+            Action::CreateDir => self.totals.dirs_to_create += 1,
+            Action::Copy => self.totals.files_to_copy += 1,
+            Action::Overwrite => self.totals.files_to_overwrite += 1,
+            Action::Skip => self.totals.skipped += 1,
+            Action::Exclude => self.totals.excluded += 1,
+            Action::SetAttributes => self.totals.attribute_changes += 1,
+        }
+        if matches!(action, Action::Copy | Action::Overwrite) {
+            self.totals.bytes += size.unwrap_or(0);
+        }
+        if action == Action::CreateDir {
+            self.planned_dirs.insert(destination.to_path_buf());
+        }
+
+        self.entries.push(PlanEntry {
+            action,
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            size,
+            reason,
+        });
+    }
+
+    // 被排除但仍被遍历的目录不会单独创建，复制其中的文件时才创建
+    fn push_missing_parents(&mut self, src: &Path, dst: &Path) {
+        let missing: Vec<(&Path, &Path)> = src
+            .ancestors()
+            .zip(dst.ancestors())
+            .skip(1)
+            .take_while(|(_, dst)| !dst.as_os_str().is_empty() && !dst.exists() && !self.planned_dirs.contains(*dst))
+            .collect();
+
+        for (src, dst) in missing.into_iter().rev() {
+            self.push(Action::CreateDir, src, dst, None, None);
+        }
+    }
+
+    fn push_file(&mut self, src: &Path, dst: &Path, cli: &Cli) -> Result<()> {
+        let size = src.metadata()?.len();
+        self.push_missing_parents(src, dst);
+        if dst.exists() {
+            if cli.interactive {
+                let reason = "destination exists, will ask".to_string();
+                self.push(Action::Overwrite, src, dst, Some(size), Some(reason));
+            } else if cli.force {
+                let reason = cli.trash.then(|| "replaced file moves to trash".to_string());
+                self.push(Action::Overwrite, src, dst, Some(size), reason);
+            } else {
+                let reason = "destination exists, copy would abort without -f".to_string();
+                self.push(Action::Skip, src, dst, Some(size), Some(reason));
+                return Ok(());
+            }
+        } else {
+            self.push(Action::Copy, src, dst, Some(size), None);
+        }
+
+        if cli.preserve {
+            self.push(Action::SetAttributes, src, dst, None, None);
+        }
+        Ok(())
+    }
+
+    fn push_dir(&mut self, src: &Path, dst: &Path, cli: &Cli) {
+        if !dst.exists() {
+            self.push(Action::CreateDir, src, dst, None, None);
+        }
+        if cli.preserve {
+            self.push(Action::SetAttributes, src, dst, None, None);
+        }
+    }
+
+    pub fn print(&self, format: PlanFormat) -> Result<()> {
+        let mut stdout = io::stdout().lock();
+        match format {
+            PlanFormat::Text => self.write_text(&mut stdout)?,
+            PlanFormat::Json => {
+                serde_json::to_writer_pretty(&mut stdout, self)?;
+                writeln!(stdout)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        for entry in &self.entries {
+            let mut line = format!("{:<10} ", entry.action.label());
+            match entry.action {
+                Action::Copy | Action::Overwrite => line.push_str(&format!(
+                    "{} -> {}",
+                    entry.source.display(),
+                    entry.destination.display()
+                )),
+                Action::Exclude => line.push_str(&entry.source.display().to_string()),
+                _ => line.push_str(&entry.destination.display().to_string()),
+            }
+            if let Some(size) = entry.size {
+                line.push_str(&format!(" ({})", format_bytes(size)));
+            }
+            if let Some(reason) = &entry.reason {
+                line.push_str(&format!(" [{}]", reason));
+            }
+            writeln!(out, "{}", line)?;
+        }
+
+        let totals = &self.totals;
+        writeln!(out)?;
+        writeln!(
+            out,
+            "Dry run: {} directories to create, {} files to copy, {} to overwrite ({} total)",
+            totals.dirs_to_create,
+            totals.files_to_copy,
+            totals.files_to_overwrite,
+            format_bytes(totals.bytes)
+        )?;
+        writeln!(
+            out,
+            "         {} skipped, {} excluded, {} attribute changes",
+            totals.skipped, totals.excluded, totals.attribute_changes
+        )?;
+        Ok(())
+    }
+}
+
+/// 按照 copy_path 的规则遍历所有源，记录每个条目将执行的操作
+pub async fn build_plan(cli: &Cli) -> Result<Plan> {
+    let mut plan = Plan::default();
+
+    for src in &cli.sources {
+        let dst = &cli.destination;
+
+        if is_source_excluded(src, cli) {
+            plan.push(Action::Exclude, src, dst, None, None);
+            continue;
+        }
+
+        if src.is_file() {
+            let dst_path = resolve_target(src, dst, cli)?;
+            plan.push_file(src, &dst_path, cli)?;
+        } else if cli.recursive && src.is_dir() {
+            let new_dst = resolve_target(src, dst, cli)?;
+            plan.push_missing_parents(src, &new_dst);
+            plan.push_dir(src, &new_dst, cli);
+
+            for entry in SourceWalk::new(src, cli) {
+                let entry = entry?;
+                let path = entry.path.as_path();
+                let target_path = new_dst.join(&entry.relative);
+
+                if let Some(exclusion) = entry.excluded {
+                    let mut reason = exclusion.describe().to_string();
+                    if entry.pruned {
+                        reason.push_str(", contents not scanned");
+                    }
+                    plan.push(Action::Exclude, path, &target_path, None, Some(reason));
+                } else if entry.is_dir {
+                    plan.push_dir(path, &target_path, cli);
+                } else if entry.is_file {
+                    plan.push_file(path, &target_path, cli)?;
+                } else {
+                    let reason = "not a regular file".to_string();
+                    plan.push(Action::Skip, path, &target_path, None, Some(reason));
+                }
+            }
+        } else if cli.source_root.is_some() && src.is_dir() {
+            let new_dst = resolve_target(src, dst, cli)?;
+            plan.push_missing_parents(src, &new_dst);
+            plan.push_dir(src, &new_dst, cli);
+        } else if src.is_dir() {
+            bail!("Source '{}' is a directory. Use -r flag for recursive copy.", src.display());
+        } else {
+            bail!("Source '{}' does not exist or is not accessible.", src.display());
+        }
+    }
+
+    Ok(plan)
+}
@@ -1,61 +1,614 @@
-use std::io::{self, stdout};
+use std::collections::VecDeque;
+use std::io::{self, stdout, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread::{self, JoinHandle};
 use std::time::{Duration, Instant};
+use parking_lot::Mutex;
 use crossterm::{
     execute,
     terminal::{disable_raw_mode, enable_raw_mode},
     cursor::{Hide, Show, MoveTo},
-    event::{self, Event, KeyCode},
+    style::Print,
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
 };
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
-    widgets::{Block, Borders, Gauge, Paragraph},
-    Terminal, backend::CrosstermBackend,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, Gauge, Paragraph, Sparkline},
+    Terminal, TerminalOptions, Viewport, backend::CrosstermBackend,
     text::{Line, Span},
 };
+use crate::cli::ProgressMode;
+use crate::copy::{ConflictAnswer, ConflictChoice, CopyControl, LogEvent, LogKind, ScanTotals};
+use crate::events::{self, EventStream};
+use crate::format::{format_bytes, format_count, format_duration, DateTime};
 
+// 交互模式下显示在进度条下方的冲突提示
+struct ConflictPrompt {
+    path: PathBuf,
+    destination: String,
+    details: Vec<String>,
+    show_details: bool,
+    apply_to_all: bool,
+    rename_input: Option<String>,
+    error: Option<String>,
+}
+
+impl ConflictPrompt {
+    fn new(src: &Path, dst: &Path) -> Self {
+        let describe = |label: &str, path: &Path, other: &Path| -> String {
+            match path.metadata() {
+                Ok(meta) => {
+                    let modified = meta.modified().ok();
+                    let newer = match (modified, other.metadata().and_then(|m| m.modified()).ok()) {
+                        (Some(a), Some(b)) if a > b => "  (newer)",
+                        _ => "",
+                    };
+                    format!(
+                        "{:<12} {:>12}  modified {}{}",
+                        label,
+                        format_bytes(meta.len()),
+                        modified.map(|t| DateTime::from_system_time(t).to_string()).unwrap_or_else(|| "-".into()),
+                        newer
+                    )
+                }
+                Err(e) => format!("{:<12} {}", label, e),
+            }
+        };
+
+        Self {
+            path: dst.to_path_buf(),
+            destination: dst.display().to_string(),
+            details: vec![describe("Source:", src, dst), describe("Destination:", dst, src)],
+            show_details: false,
+            apply_to_all: false,
+            rename_input: None,
+            error: None,
+        }
+    }
+
+    fn lines(&self) -> Vec<Line<'static>> {
+        let mut lines = vec![Line::from(Span::styled(
+            self.destination.clone(),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        ))];
+
+        if self.show_details {
+            lines.extend(self.details.iter().map(|d| Line::from(d.clone())));
+        }
+
+        match &self.rename_input {
+            Some(input) => lines.push(Line::from(format!("New name: {}_   (Enter to confirm, Esc to cancel)", input))),
+            None => lines.push(Line::from(format!(
+                "[o]verwrite  [s]kip  [r]ename  keep [n]ewer  [d]etails  [a]pply to all: {}",
+                if self.apply_to_all { "on" } else { "off" }
+            ))),
+        }
+
+        if let Some(error) = &self.error {
+            lines.push(Line::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+        }
+        lines
+    }
+}
+
+type InlineTerminal = Terminal<CrosstermBackend<io::Stdout>>;
+
+// TUI 占用的行数，不含日志面板和冲突提示
+const TUI_HEIGHT: u16 = 13;
+
+// 展开的日志面板占用的行数（含边框）
+const LOG_HEIGHT: u16 = 12;
+
+// 日志最多保留的条目数，更早的条目会被丢弃
+const LOG_CAPACITY: usize = 1000;
+
+// 复制结束时日志面板关闭，等待按 l 打开日志的时间
+const REVIEW_WAIT: Duration = Duration::from_secs(3);
+
+// 渲染任务的帧间隔，限制重绘频率
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+
+// 吞吐量历史的采样间隔和保留的点数，点数用完后相邻两点合并，历史始终覆盖整个复制过程
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(500);
+const HISTORY_LEN: usize = 512;
+
+// 纯文本模式下两次进度输出之间的间隔
+const PLAIN_INTERVAL: Duration = Duration::from_secs(2);
+
+// JSON 模式下两次字节进度事件之间的间隔
+const JSON_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Default)]
+struct CurrentFile {
+    name: String,
+    size: u64,
+    // 是否是真正在复制的文件，而不是开始前显示的标题
+    active: bool,
+}
+
+// 复制过程和渲染任务共享的状态，热路径上只有原子操作
+#[derive(Default)]
+struct Shared {
+    bytes: AtomicU64,
+    file_bytes: AtomicU64,
+    files_done: AtomicU64,
+    current_file: Mutex<CurrentFile>,
+    stop: AtomicBool,
+    interrupted: AtomicBool,
+    paused: AtomicBool,
+    skip: AtomicBool,
+    abort: AtomicBool,
+    log: Mutex<VecDeque<(LogKind, String)>>,
+    // 详细模式和 JSON 事件流等待输出的事件，不受日志容量限制
+    verbose: bool,
+    queue_events: bool,
+    pending_events: Mutex<Vec<LogEvent>>,
+}
+
+impl Shared {
+    // 当前文件已经全部复制或跳过时计入完成数
+    fn complete_file(&self, file: &mut CurrentFile) {
+        if file.active && self.file_bytes.load(Ordering::Relaxed) >= file.size {
+            self.files_done.fetch_add(1, Ordering::Relaxed);
+        }
+        file.active = false;
+    }
+}
+
+struct ConflictRequest {
+    src: PathBuf,
+    dst: PathBuf,
+    reply: mpsc::Sender<io::Result<ConflictAnswer>>,
+}
+
+/// 复制过程向进度显示发布计数的句柄，可以随意克隆，不会等待渲染
+#[derive(Clone)]
+pub struct ProgressHandle {
+    shared: Arc<Shared>,
+    requests: mpsc::Sender<ConflictRequest>,
+}
+
+impl ProgressHandle {
+    pub fn inc_current(&self, delta: u64) {
+        self.shared.bytes.fetch_add(delta, Ordering::Relaxed);
+        self.shared.file_bytes.fetch_add(delta, Ordering::Relaxed);
+    }
+
+    /// 开始复制前显示的标题，不计入文件数
+    pub fn set_title(&self, title: &str, size: u64) {
+        let mut file = self.shared.current_file.lock();
+        *file = CurrentFile {
+            name: title.to_string(),
+            size,
+            active: false,
+        };
+        self.shared.file_bytes.store(0, Ordering::Relaxed);
+    }
+
+    pub fn set_current_file(&self, file_name: &str, file_size: u64) {
+        let mut file = self.shared.current_file.lock();
+        self.shared.complete_file(&mut file);
+        *file = CurrentFile {
+            name: file_name.to_string(),
+            size: file_size,
+            active: true,
+        };
+        self.shared.file_bytes.store(0, Ordering::Relaxed);
+        // 上一个文件结束后才到达的跳过请求不应作用于新文件
+        self.shared.skip.store(false, Ordering::Relaxed);
+    }
+
+    /// 请求渲染任务询问如何处理已存在的目标文件，阻塞直到用户做出选择
+    pub fn ask_conflict(&self, src: &Path, dst: &Path) -> io::Result<ConflictAnswer> {
+        let (reply, answer) = mpsc::channel();
+        let request = ConflictRequest {
+            src: src.to_path_buf(),
+            dst: dst.to_path_buf(),
+            reply,
+        };
+        let closed = || io::Error::new(io::ErrorKind::BrokenPipe, "progress display has stopped");
+        self.requests.send(request).map_err(|_| closed())?;
+        answer.recv().map_err(|_| closed())?
+    }
+
+    /// 向活动日志追加一条记录，开始复制文件的事件只进入事件流
+    pub fn log(&self, event: LogEvent) {
+        if event.kind != LogKind::Started {
+            let mut log = self.shared.log.lock();
+            if log.len() == LOG_CAPACITY {
+                log.pop_front();
+            }
+            log.push_back((event.kind, event.message()));
+        }
+        if self.shared.queue_events {
+            self.shared.pending_events.lock().push(event);
+        }
+    }
+
+    /// Ctrl+C 时让渲染任务恢复终端并退出程序
+    pub fn interrupt(&self) {
+        self.shared.interrupted.store(true, Ordering::Relaxed);
+    }
+
+    /// 读取界面上的暂停、跳过和中止请求，跳过请求只生效一次
+    pub fn control(&self) -> CopyControl {
+        if self.shared.abort.load(Ordering::Relaxed) {
+            CopyControl::Abort
+        } else if self.shared.skip.swap(false, Ordering::Relaxed) {
+            CopyControl::Skip
+        } else if self.shared.paused.load(Ordering::Relaxed) {
+            CopyControl::Pause
+        } else {
+            CopyControl::Continue
+        }
+    }
+}
+
+/// 进度显示，由独立的渲染线程按固定帧率绘制，并负责所有终端输入
 pub struct CopyProgress {
-    terminal: Terminal<CrosstermBackend<io::Stdout>>,
-    total_bytes: u64,
-    current_bytes: u64,
-    current_file: String,
-    current_file_size: u64,
-    current_file_progress: u64,
-    last_update: Instant,  // 移除 start_time
-    last_bytes: u64,
-    last_speed: f64,
+    handle: ProgressHandle,
+    mode: ProgressMode,
+    thread: Option<JoinHandle<io::Result<()>>>,
 }
 
 impl CopyProgress {
-    pub fn new(total_bytes: u64) -> io::Result<Self> {
-        let mut stdout = stdout();
-        // 初始化时不需要预留空间，只需隐藏光标
-        execute!(stdout, Hide)?;
-        enable_raw_mode()?;
-        
-        let terminal = Terminal::new(CrosstermBackend::new(stdout))?;
-        
+    /// JSON 模式下需要传入事件流
+    pub fn new(
+        totals: &ScanTotals,
+        mode: ProgressMode,
+        verbose: bool,
+        events: Option<EventStream>,
+    ) -> io::Result<Self> {
+        // 先创建视口再进入原始模式，读取不到光标位置时退回纯文本进度，终端保持原样
+        let (terminal, mode) = match mode {
+            ProgressMode::Tui => match inline_terminal(TUI_HEIGHT) {
+                Ok(terminal) => {
+                    if let Err(e) = execute!(stdout(), Hide).and_then(|_| enable_raw_mode()) {
+                        let _ = disable_raw_mode();
+                        let _ = execute!(stdout(), Show);
+                        return Err(e);
+                    }
+                    (Some(terminal), ProgressMode::Tui)
+                }
+                Err(_) => (None, ProgressMode::Plain),
+            },
+            mode => (None, mode),
+        };
+
+        let shared = Arc::new(Shared {
+            verbose,
+            queue_events: verbose || events.is_some(),
+            ..Shared::default()
+        });
+        let (requests, receiver) = mpsc::channel();
         let now = Instant::now();
-        Ok(Self {
+        let renderer = Renderer {
             terminal,
-            total_bytes,
+            viewport_height: TUI_HEIGHT,
+            resized: false,
+            events,
+            mode,
+            shared: Arc::clone(&shared),
+            requests: receiver,
+            total_bytes: totals.bytes,
+            total_files: totals.files,
+            total_dirs: totals.dirs,
             current_bytes: 0,
+            files_done: 0,
             current_file: String::new(),
             current_file_size: 0,
             current_file_progress: 0,
+            start_time: now,
             last_update: now,
             last_bytes: 0,
             last_speed: 0.0,
+            last_sample: now,
+            sample_bytes: 0,
+            sample_waited: false,
+            speed_history: SpeedHistory::default(),
+            min_speed: None,
+            peak_speed: 0.0,
+            conflict: None,
+            confirm_abort: false,
+            log_open: false,
+            log_scroll: 0,
+            done: false,
+            verbose_lines: Vec::new(),
+            last_report: now,
+        };
+
+        Ok(Self {
+            handle: ProgressHandle { shared, requests },
+            mode,
+            thread: Some(thread::spawn(move || renderer.run())),
         })
     }
 
+    /// 实际使用的显示方式，无法创建 TUI 时是纯文本
+    pub fn mode(&self) -> ProgressMode {
+        self.mode
+    }
+
+    pub fn handle(&self) -> ProgressHandle {
+        self.handle.clone()
+    }
+
+    /// 停止渲染任务，绘制最后一帧并恢复终端
+    pub fn finish(&mut self) -> io::Result<()> {
+        let Some(thread) = self.thread.take() else {
+            return Ok(());
+        };
+        self.handle.shared.stop.store(true, Ordering::Relaxed);
+        thread
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("progress display panicked")))
+    }
+}
+
+impl Drop for CopyProgress {
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+// 渲染线程独占的状态
+struct Renderer {
+    // 只有 TUI 模式才会接管终端
+    terminal: Option<InlineTerminal>,
+    // 当前内联视口请求的高度，以及终端尺寸是否已经改变
+    viewport_height: u16,
+    resized: bool,
+    events: Option<EventStream>,
+    mode: ProgressMode,
+    shared: Arc<Shared>,
+    requests: mpsc::Receiver<ConflictRequest>,
+    total_bytes: u64,
+    total_files: u64,
+    total_dirs: u64,
+    current_bytes: u64,
+    files_done: u64,
+    current_file: String,
+    current_file_size: u64,
+    current_file_progress: u64,
+    start_time: Instant,
+    last_update: Instant,
+    last_bytes: u64,
+    last_speed: f64,
+    last_sample: Instant,
+    sample_bytes: u64,
+    // 本次采样间隔内是否暂停过或等待过冲突回答
+    sample_waited: bool,
+    // 每个采样间隔内的吞吐量（字节/秒），用于绘制历史曲线
+    speed_history: SpeedHistory,
+    // 整个复制过程中的最低和最高吞吐量，暂停和等待回答时不计入最低值
+    min_speed: Option<f64>,
+    peak_speed: f64,
+    conflict: Option<(ConflictPrompt, mpsc::Sender<io::Result<ConflictAnswer>>)>,
+    // 按下 q 后等待确认
+    confirm_abort: bool,
+    log_open: bool,
+    // 日志向上滚动的行数，0 表示跟随最新条目
+    log_scroll: usize,
+    // 复制已经结束，正在绘制最后一帧
+    done: bool,
+    // 还没有打印的详细输出，视口占满终端时暂存，等到有空间或视口清除后再打印
+    verbose_lines: Vec<String>,
+    last_report: Instant,
+}
+
+impl Renderer {
+    fn run(mut self) -> io::Result<()> {
+        let result = self.render_loop();
+
+        // 最后一帧使用最终的计数
+        {
+            let mut file = self.shared.current_file.lock();
+            self.shared.complete_file(&mut file);
+        }
+        self.sync();
+        self.done = true;
+        let final_frame = self.flush_events().and_then(|_| match self.mode {
+            ProgressMode::Tui => self.draw().and_then(|_| self.review_log()),
+            ProgressMode::Plain => {
+                self.print_line();
+                Ok(())
+            }
+            ProgressMode::Json => self.emit_progress(),
+            ProgressMode::None => Ok(()),
+        });
+        self.restore()?;
+        result.and(final_frame)
+    }
+
+    // 输出新的事件：JSON 模式写入事件流，详细模式打印复制的条目
+    fn flush_events(&mut self) -> io::Result<()> {
+        if !self.shared.queue_events {
+            return Ok(());
+        }
+        let pending = std::mem::take(&mut *self.shared.pending_events.lock());
+        if let Some(stream) = &self.events {
+            for event in &pending {
+                stream.emit(&events::Event::from_log(event))?;
+            }
+        }
+        if !self.shared.verbose {
+            return Ok(());
+        }
+
+        self.verbose_lines.extend(pending.iter().filter_map(|event| match event.kind {
+            LogKind::Copied | LogKind::Created | LogKind::Warning => Some(event.message()),
+            LogKind::Skipped => Some(format!("skipped {}", event.message())),
+            // 错误由 main 输出
+            LogKind::Started | LogKind::Error => None,
+        }));
+        self.print_verbose_lines()
+    }
+
+    // TUI 模式下打印在视口上方，没有空间时留到下次；没有视口时直接写到标准输出
+    fn print_verbose_lines(&mut self) -> io::Result<()> {
+        if self.verbose_lines.is_empty() {
+            return Ok(());
+        }
+        match self.terminal.as_mut() {
+            Some(terminal) => {
+                if print_above(terminal, &self.verbose_lines)? {
+                    self.verbose_lines.clear();
+                }
+                Ok(())
+            }
+            None => {
+                let mut stdout = stdout().lock();
+                for line in self.verbose_lines.drain(..) {
+                    writeln!(stdout, "{}", line)?;
+                }
+                stdout.flush()
+            }
+        }
+    }
+
+    // 复制结束后仍然可以查看日志：面板展开时一直显示，直到按下滚动键以外的任意键；
+    // 面板关闭时短暂等待，期间按 l 展开面板
+    fn review_log(&mut self) -> io::Result<()> {
+        if self.shared.interrupted.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+        let deadline = Instant::now() + REVIEW_WAIT;
+        loop {
+            if !self.log_open && !event::poll(deadline.saturating_duration_since(Instant::now()))? {
+                return Ok(());
+            }
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                Event::Resize(..) => {
+                    self.resized = true;
+                    self.draw()?;
+                    continue;
+                }
+                _ => continue,
+            };
+            if !self.log_open && key.code == KeyCode::Char('l') {
+                self.log_open = true;
+            } else if !self.log_open || !self.handle_scroll_key(&key) {
+                return Ok(());
+            }
+            self.draw()?;
+        }
+    }
+
+    fn render_loop(&mut self) -> io::Result<()> {
+        while !self.shared.stop.load(Ordering::Relaxed) {
+            if self.shared.interrupted.load(Ordering::Relaxed) {
+                self.exit();
+            }
+
+            self.sync();
+            self.flush_events()?;
+            if self.conflict.is_none() {
+                if let Ok(request) = self.requests.try_recv() {
+                    self.start_conflict(request)?;
+                }
+            }
+
+            match self.mode {
+                ProgressMode::Tui => {
+                    self.draw()?;
+                    self.handle_input()?;
+                }
+                ProgressMode::Plain => {
+                    if self.last_report.elapsed() >= PLAIN_INTERVAL {
+                        self.last_report = Instant::now();
+                        self.print_line();
+                    }
+                    thread::sleep(FRAME_INTERVAL);
+                }
+                ProgressMode::Json => {
+                    if self.last_report.elapsed() >= JSON_INTERVAL {
+                        self.last_report = Instant::now();
+                        self.emit_progress()?;
+                    }
+                    thread::sleep(FRAME_INTERVAL);
+                }
+                ProgressMode::None => thread::sleep(FRAME_INTERVAL),
+            }
+        }
+        Ok(())
+    }
+
+    // 从共享状态读取最新的计数，并更新平滑后的速度
+    fn sync(&mut self) {
+        {
+            let file = self.shared.current_file.lock();
+            if file.name != self.current_file {
+                self.current_file = file.name.clone();
+            }
+            self.current_file_size = file.size;
+        }
+        self.current_bytes = self.shared.bytes.load(Ordering::Relaxed);
+        self.current_file_progress = self.shared.file_bytes.load(Ordering::Relaxed);
+        self.files_done = self.shared.files_done.load(Ordering::Relaxed);
+
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        if elapsed >= 0.1 {
+            self.last_speed = self.calculate_speed();
+            self.last_update = now;
+            self.last_bytes = self.current_bytes;
+        }
+
+        self.sample_waited |= self.shared.paused.load(Ordering::Relaxed) || self.conflict.is_some();
+        let elapsed = now.duration_since(self.last_sample);
+        if elapsed >= SAMPLE_INTERVAL {
+            self.record_sample(elapsed);
+            self.last_sample = now;
+            self.sample_bytes = self.current_bytes;
+        }
+    }
+
+    // 记录一个采样间隔内未经平滑的吞吐量，停顿会如实显示为低谷
+    fn record_sample(&mut self, elapsed: Duration) {
+        let bytes = self.current_bytes.saturating_sub(self.sample_bytes);
+        let speed = bytes as f64 / elapsed.as_secs_f64();
+
+        self.speed_history.push(speed);
+
+        self.peak_speed = self.peak_speed.max(speed);
+        if !self.sample_waited && self.current_bytes < self.total_bytes {
+            self.min_speed = Some(self.min_speed.map_or(speed, |min| min.min(speed)));
+        }
+        self.sample_waited = false;
+    }
+
+    // 整个复制过程的平均吞吐量（字节/秒）
+    fn average_speed(&self) -> f64 {
+        let elapsed = self.start_time.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            self.current_bytes as f64 / elapsed
+        } else {
+            0.0
+        }
+    }
+
+    fn throughput_title(&self) -> String {
+        let mib = |bytes_per_sec: f64| bytes_per_sec / (1024.0 * 1024.0);
+        let min = self.min_speed.map_or_else(|| "--".to_string(), |min| format!("{:.2}", mib(min)));
+        format!(
+            "Throughput  min {}  avg {:.2}  peak {:.2} MiB/s",
+            min,
+            mib(self.average_speed()),
+            mib(self.peak_speed)
+        )
+    }
+
     fn calculate_speed(&self) -> f64 {
         let elapsed = self.last_update.elapsed().as_secs_f64();
         if elapsed < 0.1 {
             return self.last_speed;
         }
 
-        let bytes_per_sec = (self.current_bytes - self.last_bytes) as f64 / elapsed;
+        let bytes_per_sec = self.current_bytes.saturating_sub(self.last_bytes) as f64 / elapsed;
         let speed = bytes_per_sec / (1024.0 * 1024.0);
 
         // 使用更平滑的移动平均
@@ -66,60 +619,339 @@ impl CopyProgress {
         }
     }
 
-    pub fn set_current_file(&mut self, file_name: &str, file_size: u64) {
-        self.current_file = file_name.to_string();
-        self.current_file_size = file_size;
-        self.current_file_progress = 0;
-        self.redraw().unwrap();
+    // 根据剩余字节数和平滑后的速度估计剩余时间
+    fn eta(&self) -> Option<Duration> {
+        let remaining = self.total_bytes.saturating_sub(self.current_bytes);
+        if remaining == 0 {
+            return Some(Duration::ZERO);
+        }
+
+        let bytes_per_sec = self.last_speed * 1024.0 * 1024.0;
+        (bytes_per_sec >= 1.0).then(|| Duration::from_secs_f64(remaining as f64 / bytes_per_sec))
     }
 
-    pub fn inc_current(&mut self, delta: u64) {
-        self.current_bytes += delta;
-        self.current_file_progress += delta;
+    fn stats_line(&self) -> String {
+        format!(
+            "Elapsed: {}    ETA: {}    {} / {} files, {} dirs",
+            format_duration(self.start_time.elapsed()),
+            self.eta().map(format_duration).unwrap_or_else(|| "--:--".to_string()),
+            format_count(self.files_done),
+            format_count(self.total_files),
+            format_count(self.total_dirs)
+        )
+    }
 
-        let now = Instant::now();
-        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+    fn start_conflict(&mut self, request: ConflictRequest) -> io::Result<()> {
+        if self.terminal.is_none() {
+            let answer = self.ask_conflict_line(&request.dst);
+            let _ = request.reply.send(answer);
+            return Ok(());
+        }
+        self.conflict = Some((ConflictPrompt::new(&request.src, &request.dst), request.reply));
+        Ok(())
+    }
 
-        // 更频繁地更新速度
-        if elapsed >= 0.1 {
-            let bytes_per_sec = (self.current_bytes - self.last_bytes) as f64 / elapsed;
-            let speed = bytes_per_sec / (1024.0 * 1024.0);
+    // 等待下一帧期间处理按键
+    fn handle_input(&mut self) -> io::Result<()> {
+        let deadline = Instant::now() + FRAME_INTERVAL;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            if !event::poll(timeout)? {
+                return Ok(());
+            }
 
-            // 更平滑的速度更新
-            self.last_speed = if self.last_speed > 0.0 {
-                self.last_speed * 0.8 + speed * 0.2
-            } else {
-                speed
+            let key = match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => key,
+                Event::Resize(..) => {
+                    self.resized = true;
+                    return Ok(());
+                }
+                _ => continue,
             };
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL) {
+                self.exit();
+            }
 
-            self.last_update = now;
-            self.last_bytes = self.current_bytes;
+            if self.handle_scroll_key(&key) {
+                // 方向键在冲突提示中也可以滚动日志
+            } else if self.conflict.is_none() {
+                self.handle_control_key(&key);
+            } else if let Some(choice) = self.handle_conflict_key(&key) {
+                if let Some((prompt, reply)) = self.conflict.take() {
+                    let apply_to_all = prompt.apply_to_all;
+                    let _ = reply.send(Ok(ConflictAnswer { choice, apply_to_all }));
+                }
+            }
+            // 按键后立即重绘
+            return Ok(());
+        }
+    }
+
+    // 日志面板展开时用方向键滚动，返回按键是否已被处理
+    fn handle_scroll_key(&mut self, key: &KeyEvent) -> bool {
+        if !self.log_open {
+            return false;
+        }
+        let page = usize::from(LOG_HEIGHT - 2);
+        let max_scroll = self.shared.log.lock().len().saturating_sub(page);
+        self.log_scroll = match key.code {
+            KeyCode::Up => self.log_scroll + 1,
+            KeyCode::Down => self.log_scroll.saturating_sub(1),
+            KeyCode::PageUp => self.log_scroll + page,
+            KeyCode::PageDown => self.log_scroll.saturating_sub(page),
+            KeyCode::Home => max_scroll,
+            KeyCode::End => 0,
+            _ => return false,
+        }
+        .min(max_scroll);
+        true
+    }
+
+    // 日志面板中可见的条目，按时间顺序
+    fn visible_log(&self) -> (Vec<Line<'static>>, usize) {
+        let log = self.shared.log.lock();
+        let rows = usize::from(LOG_HEIGHT - 2);
+        let end = log.len() - self.log_scroll.min(log.len().saturating_sub(rows));
+        let start = end.saturating_sub(rows);
+        let lines = log
+            .range(start..end)
+            .map(|(kind, message)| {
+                let (label, color) = match kind {
+                    LogKind::Started => ("started", Color::Blue),
+                    LogKind::Copied => ("copied ", Color::Green),
+                    LogKind::Created => ("created", Color::Cyan),
+                    LogKind::Skipped => ("skipped", Color::Yellow),
+                    LogKind::Warning => ("warning", Color::Magenta),
+                    LogKind::Error => ("error  ", Color::Red),
+                };
+                Line::from(vec![
+                    Span::styled(label, Style::default().fg(color)),
+                    Span::raw(" "),
+                    Span::raw(message.clone()),
+                ])
+            })
+            .collect();
+        (lines, log.len())
+    }
+
+    fn height(&self) -> u16 {
+        if self.log_open {
+            TUI_HEIGHT + LOG_HEIGHT
+        } else {
+            TUI_HEIGHT
+        }
+    }
+
+    // p 暂停/继续，s 跳过当前文件，q 确认后中止，l 展开/收起日志
+    fn handle_control_key(&mut self, key: &KeyEvent) {
+        if self.confirm_abort {
+            self.confirm_abort = false;
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Char('Y')) {
+                self.shared.abort.store(true, Ordering::Relaxed);
+                self.shared.paused.store(false, Ordering::Relaxed);
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Char('p') => {
+                self.shared.paused.fetch_xor(true, Ordering::Relaxed);
+            }
+            KeyCode::Char('s') => self.shared.skip.store(true, Ordering::Relaxed),
+            KeyCode::Char('q') => self.confirm_abort = true,
+            KeyCode::Char('l') => {
+                self.log_open = !self.log_open;
+                self.log_scroll = 0;
+            }
+            _ => {}
+        }
+    }
+
+    fn footer(&self) -> Line<'static> {
+        if self.conflict.is_some() {
+            return Line::default();
+        }
+        let aborted = self.shared.abort.load(Ordering::Relaxed);
+        if aborted && !self.done {
+            return Line::from(Span::styled("Aborting...", Style::default().fg(Color::Red)));
+        }
+        if self.done {
+            if self.shared.interrupted.load(Ordering::Relaxed) {
+                return Line::default();
+            }
+            let status = if aborted {
+                Span::styled("Aborted.", Style::default().fg(Color::Red))
+            } else {
+                Span::styled("Finished.", Style::default().fg(Color::DarkGray))
+            };
+            let keys = if self.log_open {
+                "  [↑↓] scroll log  any other key to exit"
+            } else {
+                "  [l] view log  any other key to exit"
+            };
+            return Line::from(vec![status, Span::styled(keys, Style::default().fg(Color::DarkGray))]);
+        }
+        if self.confirm_abort {
+            return Line::from(Span::styled(
+                "Abort the copy? [y/N]",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            ));
         }
 
-        self.redraw().unwrap();
+        let paused = self.shared.paused.load(Ordering::Relaxed);
+        let mut spans = Vec::new();
+        if paused {
+            spans.push(Span::styled(" PAUSED ", Style::default().fg(Color::Black).bg(Color::Yellow)));
+            spans.push(Span::raw("  "));
+        }
+        let mut keys = format!(
+            "[p] {}  [s] skip file  [q] quit  [l] {} log",
+            if paused { "resume" } else { "pause" },
+            if self.log_open { "hide" } else { "show" }
+        );
+        if self.log_open {
+            keys.push_str("  [↑↓] scroll");
+        }
+        spans.push(Span::styled(keys, Style::default().fg(Color::DarkGray)));
+        Line::from(spans)
     }
 
-    fn redraw(&mut self) -> io::Result<()> {
-        // 检查 Ctrl+C
-        if event::poll(Duration::from_millis(0))? {
-            if let Event::Key(key) = event::read()? {
-                if key.code == KeyCode::Char('c') && key.modifiers.contains(event::KeyModifiers::CONTROL) {
-                    self.finish()?;
-                    std::process::exit(0);
+    // 处理冲突提示中的按键，做出选择时返回
+    fn handle_conflict_key(&mut self, key: &KeyEvent) -> Option<ConflictChoice> {
+        let (prompt, _) = self.conflict.as_mut()?;
+        prompt.error = None;
+        let dst = prompt.path.as_path();
+
+        if let Some(input) = prompt.rename_input.as_mut() {
+            match key.code {
+                KeyCode::Enter => {
+                    let name = input.trim().to_string();
+                    if name.is_empty() || name.contains(std::path::is_separator) {
+                        prompt.error = Some("Invalid file name".to_string());
+                    } else if dst.with_file_name(&name).exists() {
+                        prompt.error = Some(format!("'{}' already exists", name));
+                    } else {
+                        return Some(ConflictChoice::Rename(name));
+                    }
                 }
+                KeyCode::Esc => prompt.rename_input = None,
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Char(c) => input.push(c),
+                _ => {}
+            }
+            return None;
+        }
+
+        match key.code {
+            KeyCode::Char('o') => return Some(ConflictChoice::Overwrite),
+            KeyCode::Char('s') => return Some(ConflictChoice::Skip),
+            KeyCode::Char('n') => return Some(ConflictChoice::KeepNewer),
+            KeyCode::Char('r') => {
+                let name = dst.file_name().unwrap_or_default().to_string_lossy().to_string();
+                prompt.rename_input = Some(name);
+            }
+            KeyCode::Char('d') => prompt.show_details = !prompt.show_details,
+            KeyCode::Char('a') => prompt.apply_to_all = !prompt.apply_to_all,
+            _ => {}
+        }
+        None
+    }
+
+    // 没有 TUI 时在标准错误上逐行询问
+    fn ask_conflict_line(&self, dst: &Path) -> io::Result<ConflictAnswer> {
+        let read_answer = |prompt: &str| -> io::Result<String> {
+            eprint!("{}", prompt);
+            io::stderr().flush()?;
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input)? == 0 {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "no answer for existing destination"));
             }
+            Ok(input.trim().to_string())
+        };
+
+        loop {
+            let answer = read_answer(&format!(
+                "'{}' exists. [o]verwrite [s]kip [r]ename keep [n]ewer (append 'a' to apply to all): ",
+                dst.display()
+            ))?;
+            let apply_to_all = answer.len() == 2 && answer.ends_with('a');
+            let choice = match answer.chars().next() {
+                Some('o') => ConflictChoice::Overwrite,
+                Some('s') => ConflictChoice::Skip,
+                Some('n') => ConflictChoice::KeepNewer,
+                Some('r') => {
+                    let name = read_answer("New name: ")?;
+                    if name.is_empty() || name.contains(std::path::is_separator) {
+                        eprintln!("Invalid file name");
+                        continue;
+                    } else if dst.with_file_name(&name).exists() {
+                        eprintln!("'{}' already exists", name);
+                        continue;
+                    }
+                    ConflictChoice::Rename(name)
+                }
+                _ => continue,
+            };
+            return Ok(ConflictAnswer { choice, apply_to_all });
         }
+    }
+
+    fn print_line(&self) {
+        let percent = (self.current_bytes as f64 / self.total_bytes.max(1) as f64 * 100.0).min(100.0);
+        eprintln!(
+            "{:5.1}%  {} / {}  {:.2} MiB/s  ETA {}  {} / {} files  {}",
+            percent,
+            format_bytes(self.current_bytes),
+            format_bytes(self.total_bytes),
+            self.last_speed,
+            self.eta().map(format_duration).unwrap_or_else(|| "--:--".to_string()),
+            format_count(self.files_done),
+            format_count(self.total_files),
+            self.current_file
+        );
+    }
 
+    fn emit_progress(&self) -> io::Result<()> {
+        let Some(stream) = &self.events else {
+            return Ok(());
+        };
+        stream.emit(&events::Event::Progress {
+            bytes: self.current_bytes,
+            total_bytes: self.total_bytes,
+            files_done: self.files_done,
+            total_files: self.total_files,
+            bytes_per_sec: self.last_speed * 1024.0 * 1024.0,
+        })
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
         // 预先计算所有需要的值
         let total_bytes = self.total_bytes;
         let current_bytes = self.current_bytes;
         let current_file = self.current_file.clone();
         let current_file_size = self.current_file_size;
         let current_file_progress = self.current_file_progress;
-        let speed = self.calculate_speed();
+        let speed = self.last_speed;
+        let stats = self.stats_line();
+        let conflict_lines = self.conflict.as_ref().map(|(prompt, _)| prompt.lines());
+        let footer = self.footer();
+        let throughput_title = self.throughput_title();
+        let speed_history = self.speed_history.clone();
+        let height = self.height();
+        let log = self.log_open.then(|| self.visible_log());
 
-        let total_progress = (current_bytes as f64 / total_bytes as f64 * 100.0) as u16;
-        let current_progress = (current_file_progress as f64 / current_file_size.max(1) as f64 * 100.0) as u16;
+        // 日志面板和冲突提示都画在视口内，高度变化时重新创建视口
+        let prompt_height = conflict_lines.as_ref().map_or(0, |lines| lines.len() as u16 + 2);
+        if height + prompt_height != self.viewport_height || self.resized {
+            self.reset_viewport(height + prompt_height)?;
+        }
+        let log_scroll = self.log_scroll;
+
+        let total_progress = ((current_bytes as f64 / total_bytes as f64 * 100.0) as u16).min(100);
+        let current_progress =
+            ((current_file_progress as f64 / current_file_size.max(1) as f64 * 100.0) as u16).min(100);
 
         let calculate_inner_rect = |rect: Rect| -> Rect {
             Layout::default()
@@ -129,21 +961,30 @@ impl CopyProgress {
                 .split(rect)[0]
         };
 
-        self.terminal.draw(|f| {
+        let Some(terminal) = self.terminal.as_mut() else {
+            return Ok(());
+        };
+        terminal.draw(|f| {
+            let area = f.size();
             let display_area = Rect {
-                x: 0,
-                y: 0,
-                width: f.size().width,
-                height: 7,  // 减少高度到最小需求
+                height: height.min(area.height),
+                ..area
             };
 
+            let mut constraints = vec![
+                Constraint::Length(3),  // 总进度
+                Constraint::Length(1),  // 总进度详情
+                Constraint::Length(1),  // 时间和文件计数
+                Constraint::Length(4),  // 吞吐量历史
+                Constraint::Length(3),  // 当前文件
+                Constraint::Length(1),  // 按键说明
+            ];
+            if log.is_some() {
+                constraints.push(Constraint::Length(LOG_HEIGHT));  // 日志
+            }
             let main_layout = Layout::default()
                 .direction(Direction::Vertical)
-                .constraints([
-                    Constraint::Length(3),  // 总进度
-                    Constraint::Length(1),  // 总进度详情
-                    Constraint::Length(3),  // 当前文件
-                ])
+                .constraints(constraints)
                 .split(display_area);
 
             // 渲染总进度
@@ -170,37 +1011,191 @@ impl CopyProgress {
                 Span::raw(details)
             ]));
             f.render_widget(total_detail, main_layout[1]);
+            f.render_widget(Paragraph::new(stats), main_layout[2]);
+
+            // 渲染吞吐量历史，采样多于宽度时压缩显示整个过程
+            let throughput_block = Block::default()
+                .title(throughput_title)
+                .borders(Borders::ALL);
+            let width = usize::from(throughput_block.inner(main_layout[3]).width);
+            let history = speed_history.scaled(width);
+            let sparkline = Sparkline::default()
+                .block(throughput_block)
+                .style(Style::default().fg(Color::Green))
+                .data(&history);
+            f.render_widget(sparkline, main_layout[3]);
 
             // 渲染当前文件
             let current_block = Block::default()
                 .title(format!("Current File: {}", current_file))
                 .borders(Borders::ALL);
-            f.render_widget(current_block, main_layout[2]);
+            f.render_widget(current_block, main_layout[4]);
 
             let current_gauge = Gauge::default()
                 .block(Block::default())
                 .gauge_style(Style::default().fg(Color::Cyan))
                 .percent(current_progress)
                 .label(format!("{}%", current_progress));
-            f.render_widget(current_gauge, calculate_inner_rect(main_layout[2]));
+            f.render_widget(current_gauge, calculate_inner_rect(main_layout[4]));
+            f.render_widget(Paragraph::new(footer), main_layout[5]);
+
+            // 渲染日志面板
+            if let Some((lines, count)) = log {
+                let mut title = format!("Log ({} entries)", format_count(count as u64));
+                if log_scroll > 0 {
+                    title.push_str(" - scrolled, [End] to follow");
+                }
+                let log_panel = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+                f.render_widget(log_panel, main_layout[6]);
+            }
+
+            // 渲染冲突提示
+            if let Some(lines) = conflict_lines {
+                let top = display_area.bottom();
+                let prompt_area = Rect {
+                    y: top,
+                    height: prompt_height.min(area.bottom() - top),
+                    ..area
+                };
+                f.render_widget(Clear, prompt_area);
+                let prompt = Paragraph::new(lines)
+                    .block(Block::default().title("Destination exists").borders(Borders::ALL));
+                f.render_widget(prompt, prompt_area);
+            }
         })?;
 
         Ok(())
     }
 
-    pub fn finish(&mut self) -> io::Result<()> {
-        execute!(
-            self.terminal.backend_mut(),
-            Show,
-            MoveTo(0, 7)
-        )?;
-        disable_raw_mode()?;
+    // 视口高度或终端尺寸改变时，清除旧视口并从原来的顶部重新创建
+    fn reset_viewport(&mut self, height: u16) -> io::Result<()> {
+        if let Some(mut terminal) = self.terminal.take() {
+            terminal.clear()?;
+            drop(terminal);
+            match inline_terminal(height) {
+                Ok(terminal) => self.terminal = Some(terminal),
+                Err(_) => self.fall_back_to_plain(),
+            }
+        }
+        self.viewport_height = height;
+        self.resized = false;
         Ok(())
     }
+
+    // 无法重新创建视口时恢复终端，改用纯文本进度继续复制，正在显示的冲突提示改为逐行询问
+    fn fall_back_to_plain(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(stdout(), Show);
+        self.mode = ProgressMode::Plain;
+        if let Some((prompt, reply)) = self.conflict.take() {
+            let _ = reply.send(self.ask_conflict_line(&prompt.path));
+        }
+    }
+
+    // 正常结束时清除视口，由调用方在原处输出总结；被中断时保留最后一帧
+    fn restore(&mut self) -> io::Result<()> {
+        if let Some(mut terminal) = self.terminal.take() {
+            if self.done {
+                terminal.clear()?;
+                execute!(terminal.backend_mut(), Show)?;
+            } else {
+                let bottom = terminal.get_frame().size().bottom();
+                execute!(terminal.backend_mut(), MoveTo(0, bottom.saturating_sub(1)), Print("\r\n"), Show)?;
+            }
+            disable_raw_mode()?;
+        }
+        self.print_verbose_lines()
+    }
+
+    fn exit(&mut self) -> ! {
+        crate::copy::remove_partial_copy();
+        let _ = self.restore();
+        std::process::exit(0);
+    }
 }
 
-impl Drop for CopyProgress {
-    fn drop(&mut self) {
-        let _ = self.finish();
+/// 吞吐量历史，每个点是若干个连续采样的平均值
+///
+/// 点数达到 `HISTORY_LEN` 时相邻两点合并，每个点代表的采样数翻倍，
+/// 所以内存占用固定，而历史仍然从复制开始一直延续到现在。
+#[derive(Clone, Default)]
+struct SpeedHistory {
+    points: Vec<f64>,
+    // 每个点包含的采样数，0 视为 1
+    per_point: usize,
+    // 尚未凑满一个点的采样之和与个数
+    pending: f64,
+    pending_count: usize,
+}
+
+impl SpeedHistory {
+    fn push(&mut self, speed: f64) {
+        let per_point = self.per_point.max(1);
+        self.pending += speed;
+        self.pending_count += 1;
+        if self.pending_count < per_point {
+            return;
+        }
+
+        self.points.push(self.pending / per_point as f64);
+        self.pending = 0.0;
+        self.pending_count = 0;
+        if self.points.len() == HISTORY_LEN {
+            self.points = self.points.chunks(2).map(|pair| pair.iter().sum::<f64>() / pair.len() as f64).collect();
+            self.per_point = per_point * 2;
+        }
+    }
+
+    /// 把整个历史按时间均分到 `width` 列，以 KiB/s 为单位（Sparkline 只接受整数）
+    fn scaled(&self, width: usize) -> Vec<u64> {
+        let mut points = self.points.clone();
+        if self.pending_count > 0 {
+            points.push(self.pending / self.pending_count as f64);
+        }
+        if width > 0 && points.len() > width {
+            points = (0..width)
+                .map(|column| {
+                    let bucket = &points[column * points.len() / width..(column + 1) * points.len() / width];
+                    bucket.iter().sum::<f64>() / bucket.len() as f64
+                })
+                .collect();
+        }
+        points.into_iter().map(|speed| (speed / 1024.0) as u64).collect()
     }
-}
\ No newline at end of file
+}
+
+// 在光标所在的位置创建内联视口，之前的终端输出保留在上方
+fn inline_terminal(height: u16) -> io::Result<InlineTerminal> {
+    let options = TerminalOptions {
+        viewport: Viewport::Inline(height),
+    };
+    Terminal::with_options(CrosstermBackend::new(stdout()), options)
+}
+
+// 在内联视口上方插入几行，按终端宽度折行；视口占满终端时无处可插，返回 false
+fn print_above(terminal: &mut InlineTerminal, lines: &[String]) -> io::Result<bool> {
+    let viewport = terminal.get_frame().size();
+    let room = usize::from(terminal.size()?.height.saturating_sub(viewport.height));
+    if room == 0 {
+        return Ok(false);
+    }
+
+    let width = usize::from(viewport.width.max(1));
+    let rows: Vec<String> = lines
+        .iter()
+        .flat_map(|line| {
+            let chars: Vec<char> = line.chars().collect();
+            chars.chunks(width).map(|chunk| chunk.iter().collect()).collect::<Vec<String>>()
+        })
+        .collect();
+
+    // 每次插入的行数不超过视口上方的空间
+    for chunk in rows.chunks(room) {
+        terminal.insert_before(chunk.len() as u16, |buf| {
+            for (y, row) in chunk.iter().enumerate() {
+                buf.set_string(0, y as u16, row, Style::default());
+            }
+        })?;
+    }
+    Ok(true)
+}
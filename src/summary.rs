@@ -0,0 +1,124 @@
+use crate::copy::{CopyStats, ScanTotals};
+use crate::format::{format_bytes, format_count, format_duration};
+use anyhow::Result;
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Outcome {
+    Completed,
+    Aborted,
+    Failed,
+}
+
+/// 复制结束后的报告，文本输出和 `--summary-json` 使用同一份数据
+#[derive(Debug, Serialize)]
+pub struct Summary {
+    pub outcome: Outcome,
+    pub files_copied: u64,
+    pub dirs_created: u64,
+    pub bytes_copied: u64,
+    pub skipped: u64,
+    pub excluded: u64,
+    /// 复制在第一个错误处停止，所以只会是 0 或 1，出错时 `error` 给出原因
+    pub failed: u64,
+    pub elapsed_secs: f64,
+    pub average_bytes_per_sec: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub skipped_mount_points: Vec<PathBuf>,
+    #[serde(skip)]
+    ignored: u64,
+    #[serde(skip)]
+    pruned: u64,
+}
+
+impl Summary {
+    pub fn new(stats: CopyStats, totals: &ScanTotals, elapsed: Duration, error: Option<&anyhow::Error>) -> Self {
+        let outcome = if stats.aborted {
+            Outcome::Aborted
+        } else if error.is_some() {
+            Outcome::Failed
+        } else {
+            Outcome::Completed
+        };
+        let elapsed_secs = elapsed.as_secs_f64();
+        let average_bytes_per_sec = if elapsed_secs > 0.0 {
+            stats.bytes_copied as f64 / elapsed_secs
+        } else {
+            0.0
+        };
+
+        Self {
+            outcome,
+            files_copied: stats.files_copied,
+            dirs_created: stats.dirs_created,
+            bytes_copied: stats.bytes_copied,
+            skipped: stats.skipped,
+            excluded: totals.excluded,
+            failed: u64::from(outcome == Outcome::Failed),
+            elapsed_secs,
+            average_bytes_per_sec,
+            error: error.map(|e| e.to_string()),
+            skipped_mount_points: totals.mount_points.clone(),
+            ignored: totals.ignored,
+            pruned: totals.pruned,
+        }
+    }
+
+    pub fn write_text(&self, out: &mut impl Write) -> io::Result<()> {
+        let outcome = match self.outcome {
+            Outcome::Completed => "Finished",
+            Outcome::Aborted => "Aborted",
+            Outcome::Failed => "Failed",
+        };
+        writeln!(
+            out,
+            "{} in {}: {} and {} copied, {} at {}/s average",
+            outcome,
+            format_duration(Duration::from_secs_f64(self.elapsed_secs)),
+            plural(self.files_copied, "file", "files"),
+            plural(self.dirs_created, "directory", "directories"),
+            format_bytes(self.bytes_copied),
+            format_bytes(self.average_bytes_per_sec as u64)
+        )?;
+
+        let mut excluded = format!("{} excluded", format_count(self.excluded));
+        if self.ignored > 0 || self.pruned > 0 {
+            excluded.push_str(&format!(
+                " ({} by ignore files, {} directories not scanned)",
+                format_count(self.ignored),
+                format_count(self.pruned)
+            ));
+        }
+        writeln!(
+            out,
+            "{} skipped, {}, {} failed",
+            format_count(self.skipped),
+            excluded,
+            format_count(self.failed)
+        )?;
+
+        for mount_point in &self.skipped_mount_points {
+            writeln!(out, "Skipped mount point '{}' (other file system).", mount_point.display())?;
+        }
+        Ok(())
+    }
+
+    pub fn write_json(&self, path: &Path) -> Result<()> {
+        let mut file = File::create(path)
+            .map_err(|e| anyhow::anyhow!("Failed to create summary file '{}': {}", path.display(), e))?;
+        serde_json::to_writer_pretty(&mut file, self)?;
+        writeln!(file)?;
+        Ok(())
+    }
+}
+
+fn plural(count: u64, one: &str, many: &str) -> String {
+    format!("{} {}", format_count(count), if count == 1 { one } else { many })
+}
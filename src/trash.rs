@@ -0,0 +1,154 @@
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+
+/// 把被替换的文件移动到 freedesktop.org 回收站，而不是直接删除
+///
+/// 与文件同一设备上的家目录回收站（`$XDG_DATA_HOME/Trash`）优先，
+/// 否则使用所在挂载点的 `.Trash/$uid` 或 `.Trash-$uid`。
+#[cfg(unix)]
+pub fn move_to_trash(path: &Path) -> Result<PathBuf> {
+    use crate::format::DateTime;
+    use std::fs::{self, OpenOptions};
+    use std::io::{ErrorKind, Write};
+    use std::os::unix::fs::MetadataExt;
+    use std::time::SystemTime;
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid file name: '{}'", path.display()))?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => fs::canonicalize(parent)?,
+        _ => std::env::current_dir()?,
+    };
+    let path = parent.join(name);
+    let device = fs::symlink_metadata(&path)?.dev();
+
+    let (trash_dir, topdir) = find_trash_dir(&path, device)?;
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+    create_private_dir(&files_dir)?;
+    create_private_dir(&info_dir)?;
+
+    // 挂载点回收站里记录相对路径，家目录回收站记录绝对路径
+    let recorded_path = match &topdir {
+        Some(topdir) => path.strip_prefix(topdir).unwrap_or(&path),
+        None => &path,
+    };
+    let info = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_path(recorded_path),
+        DateTime::from_system_time(SystemTime::now()).iso8601()
+    );
+
+    // 按规范先用 O_EXCL 创建 .trashinfo 占住名字，再移动文件本身
+    let name = name.to_string_lossy();
+    for n in 1u32.. {
+        let trashed_name = if n == 1 { name.to_string() } else { format!("{}.{}", name, n) };
+        let info_path = info_dir.join(format!("{}.trashinfo", trashed_name));
+
+        let mut info_file = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+        let trashed_path = files_dir.join(&trashed_name);
+        if trashed_path.exists() {
+            drop(info_file);
+            let _ = fs::remove_file(&info_path);
+            continue;
+        }
+
+        let result = info_file
+            .write_all(info.as_bytes())
+            .and_then(|_| fs::rename(&path, &trashed_path));
+        if let Err(e) = result {
+            let _ = fs::remove_file(&info_path);
+            bail!("Failed to move '{}' to trash: {}", path.display(), e);
+        }
+        return Ok(trashed_path);
+    }
+
+    unreachable!()
+}
+
+#[cfg(not(unix))]
+pub fn move_to_trash(path: &Path) -> Result<PathBuf> {
+    bail!(
+        "Cannot move '{}' to trash: --trash is only supported on freedesktop.org systems.",
+        path.display()
+    )
+}
+
+#[cfg(unix)]
+fn find_trash_dir(path: &Path, device: u64) -> Result<(PathBuf, Option<PathBuf>)> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")));
+
+    if let Some(data_home) = data_home {
+        let home_trash = data_home.join("Trash");
+        let existing = home_trash.ancestors().find(|dir| dir.exists());
+        if let Some(meta) = existing.and_then(|dir| dir.metadata().ok()) {
+            if meta.dev() == device {
+                return Ok((home_trash, None));
+            }
+        }
+    }
+
+    // 找到文件所在的挂载点
+    let mut topdir = path;
+    while let Some(parent) = topdir.parent() {
+        match parent.metadata() {
+            Ok(meta) if meta.dev() == device => topdir = parent,
+            _ => break,
+        }
+    }
+
+    let uid = unsafe { libc::getuid() };
+    let shared = topdir.join(".Trash");
+    if let Ok(meta) = std::fs::symlink_metadata(&shared) {
+        // 共享的 .Trash 必须是设置了粘滞位的真实目录
+        if meta.is_dir() && meta.permissions().mode() & 0o1000 != 0 {
+            let user_trash = shared.join(uid.to_string());
+            if create_private_dir(&user_trash).is_ok() {
+                return Ok((user_trash, Some(topdir.to_path_buf())));
+            }
+        }
+    }
+
+    let user_trash = topdir.join(format!(".Trash-{}", uid));
+    match create_private_dir(&user_trash) {
+        Ok(()) => Ok((user_trash, Some(topdir.to_path_buf()))),
+        Err(e) => bail!("No usable trash directory for '{}': {}", path.display(), e),
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> std::io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+
+    if dir.is_dir() {
+        return Ok(());
+    }
+    std::fs::DirBuilder::new().recursive(true).mode(0o700).create(dir)
+}
+
+// .trashinfo 中的路径按 URL 规则转义
+#[cfg(unix)]
+fn encode_path(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    let mut encoded = String::new();
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
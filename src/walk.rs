@@ -0,0 +1,179 @@
+use crate::cli::Cli;
+use crate::filter::Decision;
+use crate::ignore_files::IgnoreFiles;
+use anyhow::Result;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// 条目被排除的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exclusion {
+    Filter,
+    IgnoreFile,
+    SizeOrAge,
+    FileType,
+    OtherFileSystem,
+}
+
+impl Exclusion {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Exclusion::Filter => "matches a filter rule",
+            Exclusion::IgnoreFile => "listed in an ignore file",
+            Exclusion::SizeOrAge => "outside the size/age limits",
+            Exclusion::FileType => "not a selected file type",
+            Exclusion::OtherFileSystem => "mount point of another file system",
+        }
+    }
+}
+
+pub struct WalkEntry {
+    pub path: PathBuf,
+    // 相对于遍历根目录，用于计算目标路径
+    pub relative: PathBuf,
+    pub is_dir: bool,
+    pub is_file: bool,
+    pub excluded: Option<Exclusion>,
+    // 被排除的目录没有被遍历，其内容不会出现在后续条目中
+    pub pruned: bool,
+}
+
+/// 遍历源目录下的所有条目（不含根目录本身），并对每个条目应用过滤规则和忽略文件
+///
+/// check_overwrites、get_total_size、copy_path 和试运行共用同一套遍历，保证结果一致。
+/// 使用 `--files-from` 时遍历根目录是列出的目录，过滤规则和忽略文件仍然作用于相对源根目录的路径。
+pub struct SourceWalk<'a> {
+    cli: &'a Cli,
+    root: PathBuf,
+    // 源根目录到遍历根目录的路径，没有 --files-from 时为空
+    prefix: PathBuf,
+    entries: walkdir::IntoIter,
+    ignore_files: IgnoreFiles,
+    // -x 时源根目录所在的设备
+    root_device: Option<u64>,
+}
+
+impl<'a> SourceWalk<'a> {
+    pub fn new(root: &Path, cli: &'a Cli) -> Self {
+        let mut walker = WalkDir::new(root).min_depth(1);
+        if let Some(depth) = cli.max_depth {
+            walker = walker.max_depth(depth);
+        }
+        let root_device = cli
+            .one_file_system
+            .then(|| root.metadata().ok().and_then(|metadata| device_id(&metadata)))
+            .flatten();
+        let (base, prefix) = match cli.source_root.as_deref().map(|base| (base, root.strip_prefix(base))) {
+            Some((base, Ok(prefix))) => (base, prefix.to_path_buf()),
+            _ => (root, PathBuf::new()),
+        };
+
+        Self {
+            cli,
+            root: root.to_path_buf(),
+            prefix,
+            entries: walker.into_iter(),
+            ignore_files: IgnoreFiles::new(base, cli.gitignore),
+            root_device,
+        }
+    }
+
+    // 位于其他文件系统上的目录即挂载点，连同其内容一起跳过
+    fn crosses_device(&self, entry: &walkdir::DirEntry) -> bool {
+        match self.root_device {
+            Some(root_device) if entry.file_type().is_dir() => entry
+                .metadata()
+                .ok()
+                .and_then(|metadata| device_id(&metadata))
+                .is_some_and(|device| device != root_device),
+            _ => false,
+        }
+    }
+
+    fn exclusion(&mut self, relative: &Path, is_dir: bool) -> Option<Exclusion> {
+        match self.cli.filters.decide(relative, is_dir) {
+            Decision::Include => None,
+            Decision::Exclude => Some(Exclusion::Filter),
+            Decision::NoMatch => self
+                .ignore_files
+                .is_ignored(relative, is_dir)
+                .then_some(Exclusion::IgnoreFile),
+        }
+    }
+}
+
+impl Iterator for SourceWalk<'_> {
+    type Item = Result<WalkEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.entries.next()? {
+            Ok(entry) => entry,
+            Err(e) => return Some(Err(e.into())),
+        };
+
+        let other_file_system = self.crosses_device(&entry);
+        if other_file_system {
+            self.entries.skip_current_dir();
+        }
+
+        let path = entry.into_path();
+        let relative = match path.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(e) => return Some(Err(e.into())),
+        };
+        let is_dir = path.is_dir();
+        let is_file = path.is_file();
+        let filtered = self.prefix.join(&relative);
+        let mut excluded = if other_file_system {
+            Some(Exclusion::OtherFileSystem)
+        } else {
+            self.exclusion(&filtered, is_dir)
+        };
+
+        // 大小和修改时间只对普通文件生效
+        if excluded.is_none() && is_file && self.cli.file_filter.is_active() {
+            match path.metadata() {
+                Ok(metadata) if !self.cli.file_filter.matches(&metadata) => excluded = Some(Exclusion::SizeOrAge),
+                Ok(_) => {}
+                Err(e) => return Some(Err(e.into())),
+            }
+        }
+        if excluded.is_none() && is_file && self.cli.type_filter.is_active() && !self.cli.type_filter.matches(&path) {
+            excluded = Some(Exclusion::FileType);
+        }
+
+        // 目录内容不可能再被包含时不进入该目录
+        let pruned = is_dir
+            && match excluded {
+                Some(Exclusion::OtherFileSystem) => true,
+                Some(Exclusion::Filter) => self.cli.filters.excludes_contents(&filtered),
+                Some(Exclusion::IgnoreFile) => !self.cli.filters.has_includes(),
+                Some(Exclusion::SizeOrAge | Exclusion::FileType) | None => false,
+            };
+        if pruned && !other_file_system {
+            self.entries.skip_current_dir();
+        }
+
+        Some(Ok(WalkEntry {
+            is_file,
+            path,
+            relative,
+            is_dir,
+            excluded,
+            pruned,
+        }))
+    }
+}
+
+#[cfg(unix)]
+fn device_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.dev())
+}
+
+// 其他平台无法取得设备号，-x 不起作用
+#[cfg(not(unix))]
+fn device_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}